@@ -5,4 +5,81 @@ clipboard-image = Image
 save-title = Save Title
 unpin-item = Unpin Item
 pin-item = Pin Item
-search-entries = Search Entries
\ No newline at end of file
+conceal-item = Hide Content
+reveal-item = Show Content
+preview-image = Preview Image
+search-entries = Search Entries
+snippets = Snippets
+more-actions = More Actions
+action-open-url = Open Link
+action-compose-mail = Compose Email
+action-call = Call
+action-open-file = Open File
+action-reveal = Show in Folder
+copy-as = Copy As…
+save-as-new-entry = Save as New Entry
+transform-trim = Trimmed
+transform-collapse-lines = Single Line
+transform-uppercase = UPPERCASE
+transform-lowercase = lowercase
+transform-title-case = Title Case
+transform-strip-formatting = Plain Characters
+transform-url-encode = URL Encoded
+transform-url-decode = URL Decoded
+transform-base64-encode = Base64 Encoded
+transform-base64-decode = Base64 Decoded
+transform-json-pretty = Pretty JSON
+transform-json-minify = Minified JSON
+transform-sort-lines = Sorted Lines
+transform-dedupe-lines = Unique Lines
+transform-invalid-url-encoding = The text is not valid URL encoding
+transform-invalid-base64 = The text is not valid Base64
+transform-not-text = The decoded data is not text
+transform-invalid-json = The text is not valid JSON: { $reason }
+command-spawn-failed = Could not run the action: { $reason }
+command-timed-out = The action did not finish within { $secs } seconds
+command-failed = The action failed: { $reason }
+command-not-text = The action did not return text
+
+settings = Settings
+back = Back
+cancel = Cancel
+settings-history = History
+settings-max-history = Maximum History Entries
+settings-title-max-chars = Title Length
+settings-capture = Capture
+settings-max-image-size = Maximum Image Size
+settings-show-thumbnails = Show Image Thumbnails
+settings-thumbnail-size = Thumbnail Size
+settings-poll-interval = Poll Interval (Without Data-Control)
+settings-keep-clipboard-alive = Keep Clipboard After Apps Close
+settings-keep-clipboard-alive-description = Only text, images, rich text (HTML and RTF) and file lists stay pasteable; other formats end with the app
+settings-track-primary = Record Primary Selection
+settings-max-primary-history = Maximum Primary Selection Entries
+settings-retention = Retention
+settings-max-text-entries = Maximum Text Entries
+settings-max-image-entries = Maximum Image Entries
+settings-max-history-size = Maximum History Size
+settings-max-age = Drop Entries Not Copied For
+settings-sensitive-expiry = Drop Hidden Entries After
+settings-unlimited = Unlimited
+settings-never = Never
+primary-selection = Primary Selection
+copy-to-primary = Copy to Primary Selection
+copy-to-both = Copy to Clipboard and Primary Selection
+collection = Collection
+new-collection = New Collection
+rename-collection = Rename Collection
+delete-collection = Delete Collection
+toggle-collection = Show or Hide Collection
+move-to-collection = Move to Collection
+no-collection = No Collection
+edit-tags = Edit Tags
+add-tags = Add Tags
+query-missing-value = Search operator "{ $value }:" needs a value
+query-unknown-type = Unknown type "{ $value }", use text, plain, image, url, email, path, files, color, json, code or phone
+query-unknown-flag = Unknown flag "is:{ $value }", use is:pinned or is:primary
+query-invalid-date = Invalid date "{ $value }", use YYYY-MM-DD, today or yesterday
+query-invalid-length = Invalid length "{ $value }", use e.g. len:>500
+query-invalid-regex = Invalid regular expression /{ $value }/
+query-invalid-tag = Invalid tag "tag:{ $value }", tags are single words
@@ -1,69 +1,374 @@
-use super::{AppModel, Message};
-use crate::services::clipboard;
+use super::model::{PinnedDrag, SearchResults};
+use super::{AppModel, Message, keyboard};
+use crate::services::{clipboard, snippets};
+use crate::services::backend::SharedBackend;
+use crate::services::actions::EntryAction;
+use crate::services::commands::{self, CommandError, OutputMode};
+use crate::services::dbus::{self, EntryInfo, Request};
+use crate::services::search;
+use crate::services::query::{Query, collection_key};
+use crate::services::retention::Retention;
+use crate::services::storage::HistoryWriter;
+use crate::services::template::{PromptSession, Template};
+use crate::services::transform::{self, Transform};
 use cosmic::iced::Subscription;
+use cosmic::iced::widget::image::Handle as ImageHandle;
 use cosmic::iced_winit::commands::popup::{destroy_popup, get_popup};
 use cosmic::prelude::*;
 use futures_util::SinkExt;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 use cosmic::Action;
 use cosmic::widget::Id;
-use indexmap::IndexMap;
-use crate::services::clipboard::{ClipboardContent, ClipboardEntry};
+use crate::config::{Config, MIN_HISTORY};
+use crate::services::clipboard::{ClipboardContent, ClipboardEntry, ClipboardSource, WriteTarget};
+use cosmic::cosmic_config::CosmicConfigEntry;
 
-const MAX_HISTORY: usize = 30;
+enum WatcherEvent {
+    SelectionChanged(ClipboardSource),
+    Unavailable,
+}
+
+/// Runs the data-control watcher on its own thread, forwarding selection changes to `tx`.
+fn spawn_selection_watcher(backend: SharedBackend, tx: tokio::sync::mpsc::UnboundedSender<WatcherEvent>) {
+    std::thread::spawn(move || {
+        let events = tx.clone();
+        let result = backend.watch(Box::new(move |source| {
+            events.send(WatcherEvent::SelectionChanged(source)).is_ok()
+        }));
+
+        if let Err(err) = result {
+            if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
+                eprintln!("[clippy-land] selection watcher unavailable, polling instead: {err}");
+            }
+        }
+        _ = tx.send(WatcherEvent::Unavailable);
+    });
+}
+
+pub fn subscription(app: &AppModel) -> Subscription<Message> {
+    Subscription::batch(vec![
+        clipboard_subscription(app.config.clone(), app.clipboard.clone()),
+        snippets_subscription(),
+        keyboard::subscription(),
+        dbus_subscription(),
+        retention_subscription(&app.config),
+        app.core
+            .watch_config::<Config>(<AppModel as cosmic::Application>::APP_ID)
+            .map(|update| Message::UpdateConfig(update.config)),
+    ])
+}
 
-pub fn subscription(_app: &AppModel) -> Subscription<Message> {
+fn clipboard_subscription(config: Config, backend: SharedBackend) -> Subscription<Message> {
     struct ClipboardSubscription;
 
-    Subscription::batch(vec![Subscription::run_with_id(
+    let poll_interval = Duration::from_millis(config.poll_interval_ms);
+
+    // Keyed on the capture settings so the watcher restarts with the new limits when they change.
+    let id = (
         std::any::TypeId::of::<ClipboardSubscription>(),
+        config.max_image_bytes,
+        config.thumbnail_size_px,
+        config.poll_interval_ms,
+        config.title_max_chars,
+        config.track_primary,
+        config.keep_clipboard_alive,
+    );
+
+    let sources: &'static [ClipboardSource] = if config.track_primary {
+        &[ClipboardSource::Regular, ClipboardSource::Primary]
+    } else {
+        &[ClipboardSource::Regular]
+    };
+
+    Subscription::run_with_id(
+        id,
         cosmic::iced::stream::channel(1, move |mut channel| async move {
-            let mut last_seen: Option<clipboard::ClipboardFingerprint> = None;
+            // Each selection is deduplicated on its own.
+            let mut last_seen: HashMap<ClipboardSource, clipboard::ClipboardFingerprint> = HashMap::new();
+
+            let (tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+            spawn_selection_watcher(backend.clone(), tx);
+            let mut event_driven = true;
 
             loop {
-                tokio::time::sleep(Duration::from_millis(500)).await;
+                let changed: &[ClipboardSource] = if event_driven {
+                    match events.recv().await {
+                        Some(WatcherEvent::SelectionChanged(source)) if sources.contains(&source) => {
+                            match source {
+                                ClipboardSource::Regular => &[ClipboardSource::Regular],
+                                ClipboardSource::Primary => &[ClipboardSource::Primary],
+                            }
+                        }
+                        Some(WatcherEvent::SelectionChanged(_)) => continue,
+                        Some(WatcherEvent::Unavailable) | None => {
+                            event_driven = false;
+                            continue;
+                        }
+                    }
+                } else {
+                    tokio::time::sleep(poll_interval).await;
+                    sources
+                };
 
-                let next = tokio::task::spawn_blocking(clipboard::read_clipboard_entry)
-                    .await
-                    .ok()
-                    .flatten();
+                for &source in changed {
+                    let read_config = config.clone();
+                    let read_backend = backend.clone();
+                    let next = tokio::task::spawn_blocking(move || {
+                        clipboard::read_clipboard_entry(&*read_backend, &read_config, source)
+                    })
+                        .await
+                        .ok()
+                        .flatten();
 
-                let Some(next) = next else {
-                    continue;
-                };
+                    let Some(next) = next else {
+                        continue;
+                    };
+
+                    let next_fp = next.content.fingerprint();
+                    if last_seen.get(&source) == Some(&next_fp) {
+                        continue;
+                    }
+
+                    last_seen.insert(source, next_fp);
+
+                    // Hidden entries stay with their source, which may want to clear them later.
+                    if config.keep_clipboard_alive && source == ClipboardSource::Regular && !next.sensitive {
+                        let entry = next.clone();
+                        let write_backend = backend.clone();
+                        tokio::task::spawn_blocking(move || clipboard::keep_clipboard_alive(&*write_backend, &entry));
+                    }
+
+                    if channel.send(Message::ClipboardChanged(next)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }),
+    )
+}
+
+/// Ticks while a time-based retention rule is on, so entries expire without new copies.
+fn retention_subscription(config: &Config) -> Subscription<Message> {
+    match Retention::from_config(config).check_interval() {
+        Some(interval) => cosmic::iced::time::every(interval).map(|_| Message::EnforceRetention),
+        None => Subscription::none(),
+    }
+}
+
+/// Serves the history on the session bus, forwarding calls to `update` as `Message::Dbus`.
+fn dbus_subscription() -> Subscription<Message> {
+    struct DbusSubscription;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<DbusSubscription>(),
+        cosmic::iced::stream::channel(16, |mut channel| async move {
+            let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+            let connection = match dbus::serve(tx).await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    eprintln!("[clippy-land] D-Bus service unavailable: {err}");
+                    return std::future::pending().await;
+                }
+            };
 
-                let next_fp = next.content.fingerprint();
-                if last_seen.as_ref() == Some(&next_fp) {
-                    continue;
+            if channel.send(Message::DbusConnected(connection)).await.is_err() {
+                return;
+            }
+            while let Some(request) = requests.recv().await {
+                if channel.send(Message::Dbus(request)).await.is_err() {
+                    break;
+                }
+            }
+        }),
+    )
+}
+
+fn snippets_subscription() -> Subscription<Message> {
+    struct SnippetsSubscription;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<SnippetsSubscription>(),
+        cosmic::iced::stream::channel(1, move |mut channel| async move {
+            let Some(dir) = snippets::snippets_dir() else {
+                return;
+            };
+
+            let (tx, mut changes) = tokio::sync::mpsc::unbounded_channel();
+            // Keep the watcher alive for as long as the subscription runs.
+            let _watcher = match snippets::watch_snippets(&dir, move || {
+                _ = tx.send(());
+            }) {
+                Ok(watcher) => Some(watcher),
+                Err(err) => {
+                    eprintln!("[clippy-land] failed to watch snippets directory: {err}");
+                    None
                 }
+            };
 
-                last_seen = Some(next_fp);
+            loop {
+                let load_dir = dir.clone();
+                let loaded = tokio::task::spawn_blocking(move || snippets::load_snippets(&load_dir))
+                    .await
+                    .unwrap_or_default();
 
-                if channel.send(Message::ClipboardChanged(next)).await.is_err() {
+                if channel.send(Message::SnippetsLoaded(loaded)).await.is_err() {
                     break;
                 }
+
+                if changes.recv().await.is_none() {
+                    // Without a watcher the snippets are only loaded once.
+                    futures_util::future::pending::<()>().await;
+                }
+
+                // Editors emit several events per save; reload once they settle.
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                while changes.try_recv().is_ok() {}
             }
         }),
-    )])
+    )
 }
 
 pub fn on_clear_history(app: &mut AppModel) {
-    app.clipboard_entries.clear()
+    app.entries.retain_unpinned(|_| false)
 }
 
 pub fn on_toggle_pin_entry(app: &mut AppModel, widget_id: &Id) {
-    if app.clipboard_entries.get(widget_id).is_some() {
-        let mut entry = app.clipboard_entries.shift_remove(widget_id).expect("Moving to pinned clipboard items");
-        entry.pinned = true;
-        app.pinned_clipboard_entries.insert(widget_id.clone(), entry);
-    } else {
-        let mut entry = app.pinned_clipboard_entries.shift_remove(widget_id).expect("Moving to unpinned clipboard items");
-        entry.pinned = false;
-        app.clipboard_entries.insert(widget_id.clone(), entry);
+    if let Some(pinned) = app.entries.get(widget_id).map(|entry| entry.pinned) {
+        app.entries.set_pinned(widget_id, !pinned);
     }
 }
 
+pub fn on_toggle_sensitive(app: &mut AppModel, widget_id: &Id) {
+    if let Some(entry) = app.entries.get_mut(widget_id) {
+        entry.sensitive = !entry.sensitive;
+    }
+}
+
+/// Fills the model from the on-disk store, which keeps the entries in display order.
+pub fn restore_history(app: &mut AppModel) {
+    let Some(store) = &app.store else {
+        return;
+    };
+
+    for entry in store.load(app.config.thumbnail_size_px) {
+        app.entries.push_back(entry);
+    }
+    // Entries may have expired while the applet was not running.
+    enforce_history_limit(app);
+    sync_search(app);
+    sync_thumbnails(app);
+}
+
+/// Hands a snapshot of the history to the background writer, which saves it once changes settle.
+fn persist_history(app: &mut AppModel) {
+    let Some(store) = &app.store else {
+        return;
+    };
+
+    let writer = app
+        .history_writer
+        .get_or_insert_with(|| HistoryWriter::spawn(store.clone()));
+    // Sensitive entries are never written, so don't copy them either.
+    writer.save(app.entries.iter().filter(|entry| !entry.sensitive).cloned().collect());
+}
+
 pub fn update(app: &mut AppModel, message: Message) -> Task<cosmic::Action<Message>> {
+    // Title edits are saved once editing finishes rather than on every keystroke.
+    let mutates_history = matches!(
+        message,
+        Message::ClipboardChanged(_)
+            | Message::ClearHistory
+            | Message::RemoveHistory(_)
+            | Message::TogglePinEntry(_)
+            | Message::MovePinned { .. }
+            | Message::RenameCollection { .. }
+            | Message::DeleteCollection(_)
+            | Message::SetEntryCollection(..)
+            | Message::TagInputSubmitted(_)
+            | Message::AddTag(..)
+            | Message::RemoveTag(..)
+            | Message::ToggleSensitive(_)
+            | Message::EditToggled(_)
+            | Message::EditableInputSubmitted(_)
+            | Message::UpdateConfig(_)
+            | Message::SetMaxHistory(_)
+            | Message::SetMaxPrimaryHistory(_)
+            | Message::SetMaxTextEntries(_)
+            | Message::SetMaxImageEntries(_)
+            | Message::SetMaxHistoryMegabytes(_)
+            | Message::SetMaxAgeHours(_)
+            | Message::SetSensitiveExpirySecs(_)
+            | Message::CopyTransformed(..)
+            | Message::CustomActionFinished(..)
+    ) || matches!(&message, Message::Dbus(request) if request.mutates_history());
+
+    let task = update_model(app, message);
+    if mutates_history {
+        persist_history(app);
+    }
+    sync_search(app);
+    sync_preview(app);
+    sync_thumbnails(app);
+    task
+}
+
+/// Re-runs the search when the entries or the search text changed since its last run.
+fn sync_search(app: &mut AppModel) {
+    let computed_for = (app.entries.revision(), app.search_filter.clone());
+    if app.search_results.computed_for.as_ref() == Some(&computed_for) {
+        return;
+    }
+
+    let matches = search::search(&app.search_query, app.entries.iter())
+        .into_iter()
+        .map(|(entry, found)| (entry.widget_id.clone(), found))
+        .collect();
+    app.search_results = SearchResults {
+        computed_for: Some(computed_for),
+        matches,
+    };
+}
+
+/// Builds handles for new image thumbnails and drops those of entries that are gone.
+fn sync_thumbnails(app: &mut AppModel) {
+    let entries = &app.entries;
+    app.thumbnails.retain(|id, _| entries.get(id).is_some());
+
+    for entry in app.entries.iter() {
+        if let ClipboardContent::Image { thumbnail_png: Some(png), .. } = &entry.content {
+            app.thumbnails
+                .entry(entry.widget_id.clone())
+                .or_insert_with(|| ImageHandle::from_bytes(png.clone()));
+        }
+    }
+}
+
+/// Keeps the preview handle in line with the expanded (or else focused) image entry.
+///
+/// Hidden entries never get a preview, so one that is hidden while shown loses it.
+fn sync_preview(app: &mut AppModel) {
+    let target = app
+        .expanded_entry
+        .clone()
+        .or_else(|| app.focused_entry.clone())
+        .filter(|id| app.entries.get(id).is_some_and(|entry| !entry.sensitive));
+    if app.preview.as_ref().map(|(id, _)| id) == target.as_ref() {
+        return;
+    }
+
+    app.preview = target.and_then(|id| {
+        let entry = app.entries.get(&id)?;
+        match &entry.content {
+            ClipboardContent::Image { bytes, .. } => {
+                Some((id, ImageHandle::from_bytes(bytes.clone())))
+            }
+            ClipboardContent::Text(_) => None,
+        }
+    });
+}
+
+fn update_model(app: &mut AppModel, message: Message) -> Task<cosmic::Action<Message>> {
     match message {
         Message::ClipboardChanged(entry) => if let Some(value) = on_clipboard_changed(app, &entry) {
             return value;
@@ -73,32 +378,143 @@ pub fn update(app: &mut AppModel, message: Message) -> Task<cosmic::Action<Messa
                 return new_task;
             }
         },
+        Message::CopyToSelection(widget_id, target) => {
+            app.open_menu = None;
+            if let Some(entry) = app.entries.get(&widget_id) {
+                _ = clipboard::write_clipboard_entry_to(&*app.clipboard, entry, target);
+                return cosmic::Task::done(Message::TogglePopup).map(cosmic::Action::from);
+            }
+        }
         Message::ClearHistory => on_clear_history(app),
         Message::RemoveHistory(widget_id) => on_remove_from_history(app, widget_id),
         Message::TogglePinEntry(widget_id) => on_toggle_pin_entry(app, &widget_id),
+        Message::MovePinned { id, to } => {
+            app.entries.move_pinned(&id, to);
+        }
+        Message::PinnedDragStarted(widget_id) => {
+            app.pinned_drag = Some(PinnedDrag { id: widget_id, over: None });
+        }
+        Message::PinnedDragEntered(widget_id) => {
+            if let Some(drag) = app.pinned_drag.as_mut() {
+                drag.over = Some(widget_id);
+            }
+        }
+        Message::PinnedDropped(target) => {
+            let Some(drag) = app.pinned_drag.take() else {
+                return Task::none();
+            };
+            return on_pinned_dropped(app, drag.id, &target);
+        }
+        Message::PinnedDragCancelled => {
+            app.pinned_drag = None;
+        }
+        Message::CreateCollection(name) => return on_create_collection(app, &name),
+        Message::RenameCollection { from, to } => on_rename_collection(app, &from, &to),
+        Message::DeleteCollection(name) => on_delete_collection(app, &name),
+        Message::ToggleCollectionCollapsed(name) => {
+            if !app.collapsed_collections.remove(&name) {
+                app.collapsed_collections.insert(name);
+            }
+        }
+        Message::EditCollectionName(name) => {
+            app.collection_name_input = name.clone();
+            app.editing_collection = Some(name);
+            return cosmic::widget::text_input::focus(COLLECTION_INPUT_ID.clone());
+        }
+        Message::CollectionNameChanged(value) => {
+            app.collection_name_input = value;
+        }
+        Message::CollectionNameSubmitted(to) => {
+            app.collection_name_input.clear();
+            if let Some(from) = app.editing_collection.take() {
+                return cosmic::Task::done(Message::RenameCollection { from, to }).map(cosmic::Action::from);
+            }
+        }
+        Message::ToggleTagEditor(widget_id) => {
+            app.tag_input.clear();
+            if app.tagging_entry.as_ref() == Some(&widget_id) {
+                app.tagging_entry = None;
+            } else {
+                app.tagging_entry = Some(widget_id);
+                return cosmic::widget::text_input::focus(TAG_INPUT_ID.clone());
+            }
+        }
+        Message::TagInputChanged(value) => {
+            app.tag_input = value;
+        }
+        Message::TagInputSubmitted(value) => {
+            app.tag_input.clear();
+            if let Some(entry) = app.tagging_entry.as_ref().and_then(|id| app.entries.get_mut(id)) {
+                for tag in value.split([' ', ',']) {
+                    entry.add_tag(tag);
+                }
+            }
+        }
+        Message::AddTag(widget_id, tag) => {
+            // Picking a suggestion replaces the word it completes.
+            app.tag_input.clear();
+            if let Some(entry) = app.entries.get_mut(&widget_id) {
+                entry.add_tag(&tag);
+            }
+            return cosmic::widget::text_input::focus(TAG_INPUT_ID.clone());
+        }
+        Message::RemoveTag(widget_id, tag) => {
+            if let Some(entry) = app.entries.get_mut(&widget_id) {
+                entry.remove_tag(&tag);
+            }
+        }
+        Message::SetEntryCollection(widget_id, collection) => {
+            app.open_menu = None;
+            set_entry_collection(app, &widget_id, collection);
+        }
+        Message::ToggleSensitive(widget_id) => on_toggle_sensitive(app, &widget_id),
+        Message::TogglePreview(widget_id) => {
+            if app.expanded_entry.as_ref() == Some(&widget_id) {
+                app.expanded_entry = None;
+            } else {
+                app.expanded_entry = Some(widget_id);
+            }
+        }
+        Message::ToggleEntryMenu(widget_id) => {
+            if app.open_menu.as_ref() == Some(&widget_id) {
+                app.open_menu = None;
+            } else {
+                app.open_menu = Some(widget_id);
+            }
+            app.copy_as_expanded = false;
+            app.action_error = None;
+        }
+        Message::CloseEntryMenu => {
+            app.open_menu = None;
+            app.copy_as_expanded = false;
+        }
+        Message::RunEntryAction(widget_id, action) => {
+            return on_run_entry_action(app, &widget_id, action);
+        }
+        Message::ToggleCopyAs => {
+            app.copy_as_expanded = !app.copy_as_expanded;
+        }
+        Message::SetSaveTransformed(value) => {
+            app.save_transformed = value;
+        }
+        Message::CopyTransformed(widget_id, transform) => {
+            return on_copy_transformed(app, &widget_id, &[transform]);
+        }
+        Message::RunCustomAction(widget_id, index) => {
+            return on_run_custom_action(app, &widget_id, index);
+        }
+        Message::CustomActionFinished(output, result) => {
+            return on_custom_action_finished(app, output, result);
+        }
         Message::EditToggled(widget_id) => if let Some(value) = on_edit_toggled(app, widget_id) {
             return value;
         }
         Message::EditableInputChanged(new_value) => {
             if let Some(entry) = app.editing_entry.clone() {
-                // let title = app.history.get_mut(&entry);
-                // if title.is_none() {
-                //     return Task::none();
-                // }
-                //
-                // app.history.ge
-                if let Some(clipboard_entry) = app.clipboard_entries.get_mut(&entry) {
+                if let Some(clipboard_entry) = app.entries.get_mut(&entry) {
                     clipboard_entry.title = new_value.clone();
                 }
             }
-            // TODO : Consider using a HashMap based on the widgetId for the main tracking of items
-            // app.history.iter_mut().for_each(|entry| {
-            //     if let clipboard::ClipboardContent::Text(_) = &entry.content {
-            //         if Some(entry.widget_id.clone()) == app.editing_entry {
-            //
-            //         }
-            //     }
-            // });
         }
         Message::EditableInputSubmitted(_) => {
             app.editing_entry = None;
@@ -109,23 +525,136 @@ pub fn update(app: &mut AppModel, message: Message) -> Task<cosmic::Action<Messa
         Message::PopupClosed(id) => {
             if app.popup.as_ref() == Some(&id) {
                 app.popup = None;
+                app.focused_entry = None;
+                app.expanded_entry = None;
+                app.open_menu = None;
+                app.action_error = None;
+                app.pinned_drag = None;
+                app.editing_collection = None;
+                app.tagging_entry = None;
             }
         }
         Message::SearchInputToggled(new_value) => {
             println!("search input toggled: {}", new_value);
         }
         Message::SearchInputChanged(new_value) => {
+            (app.search_query, app.search_error) = match Query::parse(&new_value) {
+                Ok(query) => (query, None),
+                Err(err) => (Query::default(), Some(err)),
+            };
             app.search_filter = new_value;
+            app.focused_entry = None;
         }
         Message::EditableInputToggled(_) => {
             println!("Toggle edit mode for entry");
         }
+        Message::SnippetsLoaded(snippets) => {
+            app.snippets = snippets;
+        }
+        Message::CopySnippet(index) => {
+            if let Some(snippet) = app.snippets.get(index) {
+                let template = Template::parse(&snippet.content);
+                return copy_template(app, template);
+            }
+        }
+        Message::TemplateInputChanged(value) => {
+            if let Some(session) = app.prompt_session.as_mut() {
+                session.input = value;
+            }
+        }
+        Message::TemplateInputSubmitted(_) => {
+            if let Some(session) = app.prompt_session.as_mut() {
+                if let Some(text) = session.submit(&*app.clipboard) {
+                    app.prompt_session = None;
+                    _ = clipboard::write_clipboard_text(&*app.clipboard, &text);
+                    return cosmic::Task::done(Message::TogglePopup).map(cosmic::Action::from);
+                }
+                return cosmic::widget::text_input::focus(TEMPLATE_INPUT_ID.clone());
+            }
+        }
+        Message::CancelTemplate => {
+            app.prompt_session = None;
+        }
+        Message::Keyboard(action) => return keyboard::on_keyboard_action(app, action),
+        Message::DbusConnected(connection) => {
+            app.dbus = Some(connection);
+        }
+        Message::Dbus(request) => return on_dbus_request(app, request),
+        Message::ToggleSettings => {
+            app.show_settings = !app.show_settings;
+        }
+        Message::UpdateConfig(config) => {
+            app.config = config;
+            enforce_history_limit(app);
+        }
+        Message::SetMaxHistory(value) => {
+            update_config(app, |config| config.max_history = value.max(MIN_HISTORY));
+            enforce_history_limit(app);
+        }
+        Message::SetMaxImageMegabytes(value) => {
+            update_config(app, |config| config.max_image_bytes = value * 1024 * 1024);
+        }
+        Message::SetShowThumbnails(value) => {
+            update_config(app, |config| config.show_thumbnails = value);
+        }
+        Message::SetThumbnailSize(value) => {
+            update_config(app, |config| config.thumbnail_size_px = value);
+        }
+        Message::SetPollInterval(value) => {
+            update_config(app, |config| config.poll_interval_ms = value);
+        }
+        Message::SetTitleMaxChars(value) => {
+            update_config(app, |config| config.title_max_chars = value);
+        }
+        Message::SetTrackPrimary(value) => {
+            update_config(app, |config| config.track_primary = value);
+        }
+        Message::SetKeepClipboardAlive(value) => {
+            update_config(app, |config| config.keep_clipboard_alive = value);
+        }
+        Message::SetMaxPrimaryHistory(value) => {
+            update_config(app, |config| config.max_primary_history = value);
+            enforce_history_limit(app);
+        }
+        Message::SetMaxTextEntries(value) => {
+            update_config(app, |config| config.max_text_entries = value);
+            enforce_history_limit(app);
+        }
+        Message::SetMaxImageEntries(value) => {
+            update_config(app, |config| config.max_image_entries = value);
+            enforce_history_limit(app);
+        }
+        Message::SetMaxHistoryMegabytes(value) => {
+            update_config(app, |config| config.max_history_bytes = value * 1024 * 1024);
+            enforce_history_limit(app);
+        }
+        Message::SetMaxAgeHours(value) => {
+            update_config(app, |config| config.max_age_hours = value);
+            enforce_history_limit(app);
+        }
+        Message::SetSensitiveExpirySecs(value) => {
+            update_config(app, |config| config.sensitive_expiry_secs = value);
+            enforce_history_limit(app);
+        }
+        Message::EnforceRetention => {
+            // Most ticks drop nothing, so only write the history when something expired.
+            if enforce_history_limit(app) {
+                persist_history(app);
+            }
+        }
     }
     Task::none()
 }
 
 fn on_toggle_popup(app: &mut AppModel) -> Option<Task<Action<Message>>> {
     return Some(if let Some(p) = app.popup.take() {
+        app.focused_entry = None;
+        app.expanded_entry = None;
+        app.open_menu = None;
+        app.action_error = None;
+        app.pinned_drag = None;
+        app.editing_collection = None;
+        app.tagging_entry = None;
         destroy_popup(p)
     } else {
         let new_id = cosmic::iced::window::Id::unique();
@@ -153,20 +682,335 @@ fn on_edit_toggled(app: &mut AppModel, widget_id: Id) -> Option<Task<Action<Mess
     None
 }
 
+/// Runs a context action on the entry, then closes the popup like a copy does.
+fn on_run_entry_action(app: &mut AppModel, widget_id: &Id, action: EntryAction) -> Task<Action<Message>> {
+    app.open_menu = None;
+    app.action_error = None;
+
+    let Some(entry) = app.entries.get(widget_id) else {
+        return Task::none();
+    };
+
+    if let Err(err) = action.run(entry) {
+        eprintln!("[clippy-land] failed to run action {}: {err}", action.id);
+        app.action_error = Some(err.to_string());
+        return Task::none();
+    }
+    cosmic::Task::done(Message::TogglePopup).map(cosmic::Action::from)
+}
+
+/// Copies the entry's text run through `transforms`, optionally keeping the result as a new entry.
+fn on_copy_transformed(app: &mut AppModel, widget_id: &Id, transforms: &[Transform]) -> Task<Action<Message>> {
+    app.open_menu = None;
+    app.copy_as_expanded = false;
+    app.action_error = None;
+
+    let text = match app.entries.get(widget_id).map(|entry| &entry.content) {
+        Some(ClipboardContent::Text(text)) => text,
+        _ => return Task::none(),
+    };
+
+    let output = match transform::apply_pipeline(transforms, text) {
+        Ok(output) => output,
+        Err(err) => {
+            app.action_error = Some(err.to_string());
+            return Task::none();
+        }
+    };
+
+    let content = ClipboardContent::Text(output.clone());
+    let mut added = None;
+    if app.save_transformed {
+        let title = clipboard::summarize_one_line(&output, app.config.title_max_chars);
+        added = on_clipboard_changed(app, &ClipboardEntry::new(title, content));
+    } else {
+        app.ignored_capture = Some(content);
+    }
+
+    if !clipboard::write_clipboard_text(&*app.clipboard, &output) {
+        app.ignored_capture = None;
+    }
+    let close = cosmic::Task::done(Message::TogglePopup).map(cosmic::Action::from);
+    match added {
+        Some(added) => Task::batch([added, close]),
+        None => close,
+    }
+}
+
+/// Starts a custom action in the background; its result comes back as `CustomActionFinished`.
+fn on_run_custom_action(app: &mut AppModel, widget_id: &Id, index: usize) -> Task<Action<Message>> {
+    app.open_menu = None;
+    app.copy_as_expanded = false;
+    app.action_error = None;
+
+    let Some(action) = app.config.custom_actions.get(index).cloned() else {
+        return Task::none();
+    };
+    let text = match app.entries.get(widget_id).map(|entry| &entry.content) {
+        Some(ClipboardContent::Text(text)) => text.clone(),
+        _ => return Task::none(),
+    };
+
+    let output = action.output;
+    let timeout = Duration::from_secs(app.config.custom_action_timeout_secs);
+    Task::perform(commands::run_custom_action(action, text, timeout), move |result| {
+        Message::CustomActionFinished(output, result)
+    })
+    .map(cosmic::Action::from)
+}
+
+fn on_custom_action_finished(
+    app: &mut AppModel,
+    output: OutputMode,
+    result: Result<String, CommandError>,
+) -> Task<Action<Message>> {
+    let text = match result {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("[clippy-land] custom action failed: {err}");
+            app.action_error = Some(err.to_string());
+            return Task::none();
+        }
+    };
+
+    match output {
+        OutputMode::ReplaceClipboard => {
+            _ = clipboard::write_clipboard_text(&*app.clipboard, &text);
+            // The popup may have been closed while the command was running.
+            if app.popup.is_none() {
+                return Task::none();
+            }
+            cosmic::Task::done(Message::TogglePopup).map(cosmic::Action::from)
+        }
+        OutputMode::NewEntry => {
+            let title = clipboard::summarize_one_line(&text, app.config.title_max_chars);
+            on_clipboard_changed(app, &ClipboardEntry::new(title, ClipboardContent::Text(text)))
+                .unwrap_or_else(Task::none)
+        }
+        OutputMode::Discard => Task::none(),
+    }
+}
+
+/// Answers a bus call from the same state and handlers the popup uses.
+fn on_dbus_request(app: &mut AppModel, request: Request) -> Task<Action<Message>> {
+    match request {
+        Request::List(responder) => {
+            let entries = app.entries.iter().map(EntryInfo::from_entry).collect();
+            responder.respond(entries);
+        }
+        Request::Get(id, responder) => {
+            let found = app.entry_by_id(&id).filter(|entry| !entry.sensitive).map(|entry| {
+                let bytes = match &entry.content {
+                    ClipboardContent::Text(text) => text.as_bytes().to_vec(),
+                    ClipboardContent::Image { bytes, .. } => bytes.clone(),
+                };
+                (EntryInfo::from_entry(entry), bytes)
+            });
+            responder.respond(found);
+        }
+        Request::Copy(id, responder) => {
+            // Like `Get`, so hidden content can't be read back through the clipboard.
+            let found = app.entry_by_id(&id).filter(|entry| !entry.sensitive);
+            if let Some(entry) = found {
+                _ = clipboard::write_clipboard_entry(&*app.clipboard, entry);
+            }
+            responder.respond(found.is_some());
+        }
+        Request::Remove(id, responder) => {
+            let found = app.entry_by_id(&id).map(|entry| entry.widget_id.clone());
+            if let Some(widget_id) = found.clone() {
+                on_remove_from_history(app, widget_id);
+            }
+            responder.respond(found.is_some());
+        }
+        Request::Pin(id, responder) => responder.respond(set_pinned(app, &id, true)),
+        Request::Unpin(id, responder) => responder.respond(set_pinned(app, &id, false)),
+        Request::Clear(responder) => {
+            on_clear_history(app);
+            responder.respond(());
+        }
+        Request::Add(title, text, responder) => {
+            let title = if title.trim().is_empty() {
+                clipboard::summarize_one_line(&text, app.config.title_max_chars)
+            } else {
+                title
+            };
+            let entry = ClipboardEntry::new(title, ClipboardContent::Text(text));
+            // Not a clipboard change, so it must neither consume nor match `ignored_capture`.
+            let task = record_entry(app, &entry);
+            let id = app
+                .entries
+                .unpinned()
+                .next()
+                .filter(|added| added.content == entry.content)
+                .map(|added| added.id.to_string());
+            responder.respond(id);
+            return task.unwrap_or_else(Task::none);
+        }
+        Request::Search(query, responder) => {
+            let result = Query::parse(&query)
+                .map(|query| {
+                    let pinned = search::search(&query, app.entries.pinned());
+                    let unpinned = search::search(&query, app.entries.unpinned());
+                    pinned
+                        .into_iter()
+                        .chain(unpinned)
+                        .map(|(entry, _)| EntryInfo::from_entry(entry))
+                        .collect()
+                })
+                .map_err(|err| err.to_string());
+            responder.respond(result);
+        }
+    }
+    Task::none()
+}
+
+/// Pins or unpins the entry with the given stable id; false if there is none.
+fn set_pinned(app: &mut AppModel, id: &str, pinned: bool) -> bool {
+    let Some(widget_id) = app.entry_by_id(id).map(|entry| entry.widget_id.clone()) else {
+        return false;
+    };
+    app.entries.set_pinned(&widget_id, pinned)
+}
+
 fn on_remove_from_history(app: &mut AppModel, widget_id: Id) {
-    app.clipboard_entries.shift_remove(&widget_id);
+    app.entries.remove(&widget_id);
+    if app.editing_entry.as_ref() == Some(&widget_id) {
+        app.editing_entry = None;
+    }
+    if app.tagging_entry.as_ref() == Some(&widget_id) {
+        app.tagging_entry = None;
+    }
+}
+
+/// Moves the dragged pinned entry to the target's place, taking it into the target's collection.
+fn on_pinned_dropped(app: &mut AppModel, dragged: Id, target: &Id) -> Task<Action<Message>> {
+    let Some(to) = app.entries.pinned_position(target).filter(|_| dragged != *target) else {
+        return Task::none();
+    };
+
+    let collection = app.entries.get(target).and_then(|entry| entry.collection.clone());
+    if let Some(entry) = app.entries.get_mut(&dragged) {
+        entry.collection = collection;
+    }
+    cosmic::Task::done(Message::MovePinned { id: dragged, to }).map(cosmic::Action::from)
+}
+
+/// Id of the inline input of the tag editor.
+pub static TAG_INPUT_ID: std::sync::LazyLock<Id> = std::sync::LazyLock::new(|| Id::new("tag-input"));
+
+/// Id of the inline input used to rename a collection.
+pub static COLLECTION_INPUT_ID: std::sync::LazyLock<Id> = std::sync::LazyLock::new(|| Id::new("collection-name"));
+
+fn on_create_collection(app: &mut AppModel, name: &str) -> Task<Action<Message>> {
+    let base = name.trim();
+    if base.is_empty() {
+        return Task::none();
+    }
+
+    let mut name = base.to_string();
+    let mut suffix = 2;
+    while collection_name_taken(&app.config, &name, None) {
+        name = format!("{base} {suffix}");
+        suffix += 1;
+    }
+
+    update_config(app, |config| config.collections.push(name.clone()));
+    app.collection_name_input = name.clone();
+    app.editing_collection = Some(name);
+    cosmic::widget::text_input::focus(COLLECTION_INPUT_ID.clone())
+}
+
+/// Whether another collection than `except` already answers to `in:<name>`.
+fn collection_name_taken(config: &Config, name: &str, except: Option<&str>) -> bool {
+    let key = collection_key(name);
+    config
+        .collections
+        .iter()
+        .any(|existing| Some(existing.as_str()) != except && collection_key(existing) == key)
+}
+
+/// Empty or taken names leave the collection as it was.
+fn on_rename_collection(app: &mut AppModel, from: &str, to: &str) {
+    let to = to.trim();
+    if to.is_empty()
+        || !app.config.collections.iter().any(|name| name == from)
+        || collection_name_taken(&app.config, to, Some(from))
+    {
+        return;
+    }
+
+    let to = to.to_string();
+    update_config(app, |config| {
+        if let Some(name) = config.collections.iter_mut().find(|name| name.as_str() == from) {
+            *name = to.clone();
+        }
+    });
+    for entry in app.entries.iter_mut() {
+        if entry.collection.as_deref() == Some(from) {
+            entry.collection = Some(to.clone());
+        }
+    }
+    if app.collapsed_collections.remove(from) {
+        app.collapsed_collections.insert(to);
+    }
+}
+
+fn on_delete_collection(app: &mut AppModel, name: &str) {
+    update_config(app, |config| config.collections.retain(|existing| existing != name));
+    for entry in app.entries.iter_mut() {
+        if entry.collection.as_deref() == Some(name) {
+            entry.collection = None;
+        }
+    }
+    app.collapsed_collections.remove(name);
+    if app.editing_collection.as_deref() == Some(name) {
+        app.editing_collection = None;
+    }
+}
+
+fn set_entry_collection(app: &mut AppModel, widget_id: &Id, collection: Option<String>) {
+    if let Some(name) = &collection {
+        if !app.config.collections.contains(name) {
+            return;
+        }
+        app.entries.set_pinned(widget_id, true);
+    }
+
+    if let Some(entry) = app.entries.get_mut(widget_id).filter(|entry| entry.pinned) {
+        entry.collection = collection;
+    }
+}
+
+/// Id of the inline input shown while a template asks for its `{prompt:…}` values.
+pub static TEMPLATE_INPUT_ID: std::sync::LazyLock<Id> = std::sync::LazyLock::new(|| Id::new("template-prompt"));
+
+/// Copies the rendered template, or starts asking for its prompts first.
+fn copy_template(app: &mut AppModel, template: Template) -> Task<Action<Message>> {
+    match PromptSession::new(template.clone()) {
+        Some(session) => {
+            app.prompt_session = Some(session);
+            cosmic::widget::text_input::focus(TEMPLATE_INPUT_ID.clone())
+        }
+        None => {
+            let text = template.render(&Default::default(), &*app.clipboard);
+            _ = clipboard::write_clipboard_text(&*app.clipboard, &text);
+            cosmic::Task::done(Message::TogglePopup).map(cosmic::Action::from)
+        }
+    }
 }
 
 fn on_copy_from_history(app: &mut AppModel, index: Id) -> Option<Task<Action<Message>>> {
-    if let Some(entry) = app.clipboard_entries.get(&index) {
-        match &entry.content {
-            clipboard::ClipboardContent::Text(clipboard_text) => {
-                _ = clipboard::write_clipboard_text(&clipboard_text);
-            }
-            clipboard::ClipboardContent::Image { mime, bytes, .. } => {
-                _ = clipboard::write_clipboard_image(&mime, &*bytes);
+    if let Some(entry) = app.entries.get(&index) {
+        // Pinned text entries act like snippets and may contain placeholders.
+        if let (true, clipboard::ClipboardContent::Text(text)) = (entry.pinned, &entry.content) {
+            let template = Template::parse(text);
+            if template.has_placeholders() {
+                return Some(copy_template(app, template));
             }
         }
+
+        _ = clipboard::write_clipboard_entry(&*app.clipboard, entry);
         let app_task = cosmic::Task::done(Message::TogglePopup)
             .map(cosmic::Action::from);
         return Some(app_task);
@@ -181,32 +1025,71 @@ fn on_clipboard_changed(app: &mut AppModel, entry: &ClipboardEntry) -> Option<Ta
     //     }
     // }
 
+    // The marker only covers the next clipboard change: if the watcher missed our own write, a
+    // later copy of the same content is the user's and must be recorded.
+    if entry.source == ClipboardSource::Regular
+        && app.ignored_capture.take().is_some_and(|ignored| ignored == entry.content)
+    {
+        return None;
+    }
+
+    record_entry(app, entry)
+}
 
-    if let Some(existing_id) = get_entry_by_value(&app.clipboard_entries, entry.content.clone()) {
+/// Adds the entry to the history, or moves an unpinned one with the same content to the front.
+fn record_entry(app: &mut AppModel, entry: &ClipboardEntry) -> Option<Task<Action<Message>>> {
+    if let Some(existing_id) = app.entries.find_unpinned(&entry.content, entry.source) {
         // Found existing clipboard contents in clipboard history
-        let index_entry = app.clipboard_entries.get_index_entry(existing_id).expect("Should always be able to get it");
-        let index = index_entry.index();
-        app.clipboard_entries.move_index(index, 0);
+        if let Some(existing) = app.entries.get_mut(&existing_id) {
+            existing.last_copied_at = entry.last_copied_at;
+            existing.representations = entry.representations.clone();
+            for tag in &entry.tags {
+                existing.add_tag(tag);
+            }
+        }
+        app.entries.move_to_front(&existing_id);
     } else {
         // Did not find existing clipboard contents, let's add it
-        app.clipboard_entries.insert_before(0, entry.widget_id.clone(), entry.clone());
+        let mut entry = entry.clone();
+        // Copying a pinned entry or one from the other selection keeps the tags given to it.
+        let tags: Vec<String> = app
+            .entries
+            .iter()
+            .filter(|other| other.content == entry.content)
+            .flat_map(|other| other.tags.iter().cloned())
+            .collect();
+        for tag in &tags {
+            entry.add_tag(tag);
+        }
+        app.entries.push_front(entry.clone());
+        enforce_history_limit(app);
 
-        if app.clipboard_entries.len() > MAX_HISTORY {
-            app.clipboard_entries.pop();
+        if let Some(connection) = app.dbus.clone() {
+            let info = EntryInfo::from_entry(&entry);
+            return Some(Task::future(dbus::emit_entry_added(connection, info)).discard());
         }
     }
 
     None
 }
 
-fn get_entry_by_value(data: &IndexMap<Id, ClipboardEntry>, value: ClipboardContent) -> Option<usize> {
-    for (i, (id, entry)) in data.iter().enumerate() {
-        if value == entry.content {
-            return Some(i);
+/// Drops the unpinned entries the retention rules no longer allow. Returns whether any were dropped.
+fn enforce_history_limit(app: &mut AppModel) -> bool {
+    let before = app.entries.len();
+    let retention = Retention::from_config(&app.config);
+    app.entries.retain_unpinned(retention.keeper(SystemTime::now()));
+    app.entries.len() != before
+}
+
+/// Applies `change` to the in-memory config and writes it back through cosmic-config.
+fn update_config(app: &mut AppModel, change: impl FnOnce(&mut Config)) {
+    change(&mut app.config);
+
+    if let Some(handler) = &app.config_handler {
+        if let Err(err) = app.config.write_entry(handler) {
+            eprintln!("[clippy-land] failed to write config: {err:?}");
         }
     }
-
-    None
 }
 
 fn should_ignore_clipboard_entry(entry: &str) -> bool {
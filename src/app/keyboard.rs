@@ -0,0 +1,163 @@
+use super::{AppModel, Message};
+use cosmic::iced::keyboard::{self, Key, Modifiers, key::Named};
+use cosmic::iced::{Event, Subscription, event};
+use cosmic::prelude::*;
+
+/// Rows skipped by PageUp/PageDown.
+const PAGE_SIZE: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardAction {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Copy,
+    Remove,
+    TogglePin,
+    /// Alt+Up/Alt+Down, moves a pinned entry one place.
+    MovePinnedUp,
+    MovePinnedDown,
+    Rename,
+    /// Alt+1..9, zero based.
+    CopyNth(usize),
+}
+
+pub fn subscription() -> Subscription<Message> {
+    event::listen_with(|event, _status, _window| match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+            key_to_action(&key, modifiers).map(Message::Keyboard)
+        }
+        _ => None,
+    })
+}
+
+fn key_to_action(key: &Key, modifiers: Modifiers) -> Option<KeyboardAction> {
+    match key.as_ref() {
+        Key::Named(Named::ArrowUp) if modifiers.alt() => Some(KeyboardAction::MovePinnedUp),
+        Key::Named(Named::ArrowDown) if modifiers.alt() => Some(KeyboardAction::MovePinnedDown),
+        Key::Named(Named::ArrowUp) => Some(KeyboardAction::Up),
+        Key::Named(Named::ArrowDown) => Some(KeyboardAction::Down),
+        Key::Named(Named::PageUp) => Some(KeyboardAction::PageUp),
+        Key::Named(Named::PageDown) => Some(KeyboardAction::PageDown),
+        Key::Named(Named::Home) => Some(KeyboardAction::Home),
+        Key::Named(Named::End) => Some(KeyboardAction::End),
+        Key::Named(Named::Enter) => Some(KeyboardAction::Copy),
+        Key::Named(Named::Delete) => Some(KeyboardAction::Remove),
+        Key::Named(Named::F2) => Some(KeyboardAction::Rename),
+        Key::Character("p") if modifiers.control() => Some(KeyboardAction::TogglePin),
+        Key::Character(digit) if modifiers.alt() => digit
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=9).contains(n))
+            .map(|n| KeyboardAction::CopyNth(n - 1)),
+        _ => None,
+    }
+}
+
+/// Moves the focused row or turns the action into the matching per-entry message.
+pub fn on_keyboard_action(app: &mut AppModel, action: KeyboardAction) -> Task<cosmic::Action<Message>> {
+    // Keys belong to the text inputs while renaming, tagging, answering a prompt or in the settings.
+    if app.popup.is_none()
+        || app.editing_entry.is_some()
+        || app.editing_collection.is_some()
+        || app.tagging_entry.is_some()
+        || app.prompt_session.is_some()
+        || app.show_settings
+    {
+        return Task::none();
+    }
+
+    // Home, End and Delete edit the search text while there is some.
+    if matches!(action, KeyboardAction::Home | KeyboardAction::End | KeyboardAction::Remove)
+        && !app.search_filter.is_empty()
+    {
+        return Task::none();
+    }
+
+    let visible = app.visible_entry_ids();
+    if visible.is_empty() {
+        app.focused_entry = None;
+        return Task::none();
+    }
+
+    let current = app
+        .focused_entry
+        .as_ref()
+        .and_then(|focused| visible.iter().position(|id| id == focused));
+    let last = visible.len() - 1;
+
+    let target = match action {
+        KeyboardAction::Up => Some(current.map_or(last, |index| index.saturating_sub(1))),
+        KeyboardAction::Down => Some(current.map_or(0, |index| (index + 1).min(last))),
+        KeyboardAction::PageUp => Some(current.map_or(0, |index| index.saturating_sub(PAGE_SIZE))),
+        KeyboardAction::PageDown => Some(current.map_or(0, |index| (index + PAGE_SIZE).min(last))),
+        KeyboardAction::Home => Some(0),
+        KeyboardAction::End => Some(last),
+        _ => None,
+    };
+    if let Some(target) = target {
+        app.focused_entry = Some(visible[target].clone());
+        return Task::none();
+    }
+
+    let message = match action {
+        // Enter without a focused row copies the best match, handy right after searching.
+        KeyboardAction::Copy => Message::CopyFromHistory(visible[current.unwrap_or(0)].clone()),
+        KeyboardAction::CopyNth(n) => match visible.get(n) {
+            Some(id) => Message::CopyFromHistory(id.clone()),
+            None => return Task::none(),
+        },
+        KeyboardAction::Remove | KeyboardAction::TogglePin | KeyboardAction::Rename => {
+            let Some(index) = current else {
+                return Task::none();
+            };
+            let id = visible[index].clone();
+
+            match action {
+                KeyboardAction::Remove => {
+                    // Keep the focus on the row that takes the removed one's place.
+                    app.focused_entry = visible
+                        .get(index + 1)
+                        .or_else(|| index.checked_sub(1).and_then(|prev| visible.get(prev)))
+                        .cloned();
+                    Message::RemoveHistory(id)
+                }
+                KeyboardAction::TogglePin => Message::TogglePinEntry(id),
+                _ => Message::EditToggled(id),
+            }
+        }
+        KeyboardAction::MovePinnedUp | KeyboardAction::MovePinnedDown => {
+            let Some(index) = current.filter(|_| app.can_reorder_pinned()) else {
+                return Task::none();
+            };
+            let neighbor = if action == KeyboardAction::MovePinnedUp {
+                index.checked_sub(1)
+            } else {
+                Some(index + 1)
+            };
+
+            // Entries only swap places with the row next to them in the same collection.
+            let id = visible[index].clone();
+            let same_group = |other: &cosmic::widget::Id| {
+                match (app.entries.get(&id), app.entries.get(other)) {
+                    (Some(entry), Some(other)) => other.pinned && entry.pinned && entry.collection == other.collection,
+                    _ => false,
+                }
+            };
+            let Some(to) = neighbor
+                .and_then(|neighbor| visible.get(neighbor))
+                .filter(|other| same_group(other))
+                .and_then(|other| app.entries.pinned_position(other))
+            else {
+                return Task::none();
+            };
+            Message::MovePinned { id, to }
+        }
+        _ => return Task::none(),
+    };
+
+    cosmic::Task::done(message).map(cosmic::Action::from)
+}
@@ -1,4 +1,11 @@
+use super::keyboard::KeyboardAction;
+use crate::config::Config;
+use crate::services::actions::EntryAction;
 use crate::services::clipboard;
+use crate::services::commands::{CommandError, OutputMode};
+use crate::services::dbus;
+use crate::services::snippets::Snippet;
+use crate::services::transform::Transform;
 use cosmic::iced::window::Id;
 use cosmic::iced_core;
 
@@ -9,13 +16,77 @@ pub enum Message {
     PopupClosed(iced_core::window::Id),
     ClipboardChanged(clipboard::ClipboardEntry),
     ClearHistory,
-    RemoveHistory(cosmic::widget::Id), // TODO: Verify remove history still works after this change
+    RemoveHistory(cosmic::widget::Id),
     CopyFromHistory(cosmic::widget::Id),
+    CopyToSelection(cosmic::widget::Id, clipboard::WriteTarget),
     TogglePinEntry(cosmic::widget::Id),
+    /// Moves a pinned entry to position `to` among the pinned ones.
+    MovePinned { id: cosmic::widget::Id, to: usize },
+    PinnedDragStarted(cosmic::widget::Id),
+    PinnedDragEntered(cosmic::widget::Id),
+    /// The dragged entry was released over this pinned entry.
+    PinnedDropped(cosmic::widget::Id),
+    PinnedDragCancelled,
+    /// Creates a collection with this name, or a numbered variant if it is taken.
+    CreateCollection(String),
+    /// Renames a collection, keeping its entries in it.
+    RenameCollection { from: String, to: String },
+    /// Deletes a collection; its entries stay pinned outside any collection.
+    DeleteCollection(String),
+    ToggleCollectionCollapsed(String),
+    EditCollectionName(String),
+    CollectionNameChanged(String),
+    CollectionNameSubmitted(String),
+    /// Opens or closes the tag editor of an entry.
+    ToggleTagEditor(cosmic::widget::Id),
+    TagInputChanged(String),
+    /// Adds the whitespace separated tags typed in the tag editor.
+    TagInputSubmitted(String),
+    AddTag(cosmic::widget::Id, String),
+    RemoveTag(cosmic::widget::Id, String),
+    /// Moves an entry into a collection, pinning it if needed, or out of its collection.
+    SetEntryCollection(cosmic::widget::Id, Option<String>),
+    ToggleSensitive(cosmic::widget::Id),
+    TogglePreview(cosmic::widget::Id),
+    ToggleEntryMenu(cosmic::widget::Id),
+    CloseEntryMenu,
+    RunEntryAction(cosmic::widget::Id, EntryAction),
+    ToggleCopyAs,
+    SetSaveTransformed(bool),
+    CopyTransformed(cosmic::widget::Id, Transform),
+    /// Runs `config.custom_actions[index]` on the entry.
+    RunCustomAction(cosmic::widget::Id, usize),
+    CustomActionFinished(OutputMode, Result<String, CommandError>),
     SearchInputToggled(bool),
     SearchInputChanged(String),
     EditableInputToggled(bool),
     EditableInputChanged(String),
     EditToggled(cosmic::widget::Id),
     EditableInputSubmitted(String),
+    ToggleSettings,
+    UpdateConfig(Config),
+    SetMaxHistory(usize),
+    SetMaxImageMegabytes(usize),
+    SetShowThumbnails(bool),
+    SetThumbnailSize(u32),
+    SetPollInterval(u64),
+    SetTitleMaxChars(usize),
+    SetTrackPrimary(bool),
+    SetKeepClipboardAlive(bool),
+    SetMaxPrimaryHistory(usize),
+    SetMaxTextEntries(usize),
+    SetMaxImageEntries(usize),
+    SetMaxHistoryMegabytes(usize),
+    SetMaxAgeHours(u64),
+    SetSensitiveExpirySecs(u64),
+    /// Periodic check of the time-based retention rules.
+    EnforceRetention,
+    SnippetsLoaded(Vec<Snippet>),
+    CopySnippet(usize),
+    TemplateInputChanged(String),
+    TemplateInputSubmitted(String),
+    CancelTemplate,
+    Keyboard(KeyboardAction),
+    DbusConnected(zbus::Connection),
+    Dbus(dbus::Request),
 }
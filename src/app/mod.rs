@@ -1,13 +1,19 @@
 mod handlers;
+mod keyboard;
 mod messages;
 mod model;
 mod view;
 
+#[cfg(test)]
+mod tests;
+
 pub use messages::Message;
 pub use model::AppModel;
 
 use cosmic::iced::{Subscription, window::Id};
 use cosmic::prelude::*;
+use crate::config::Config;
+use crate::services::storage::HistoryStore;
 
 impl cosmic::Application for AppModel {
     type Executor = cosmic::executor::Default;
@@ -17,7 +23,7 @@ impl cosmic::Application for AppModel {
     type Message = Message;
 
     /// Unique identifier in RDNN (reverse domain name notation) format
-    const APP_ID: &'static str = "com.keewee.CosmicAppletClippyLand";
+    const APP_ID: &'static str = crate::config::APP_ID;
 
     fn core(&self) -> &cosmic::Core {
         &self.core
@@ -31,13 +37,18 @@ impl cosmic::Application for AppModel {
         core: cosmic::Core,
         _flags: Self::Flags,
     ) -> (Self, Task<cosmic::Action<Self::Message>>) {
-        (
-            AppModel {
-                core,
-                ..Default::default()
-            },
-            Task::none(),
-        )
+        let (config_handler, config) = Config::load();
+
+        let mut app = AppModel {
+            core,
+            store: HistoryStore::open_default(),
+            config,
+            config_handler,
+            ..Default::default()
+        };
+        handlers::restore_history(&mut app);
+
+        (app, Task::none())
     }
 
     fn on_close_requested(&self, id: Id) -> Option<Message> {
@@ -1,17 +1,326 @@
+use crate::config::Config;
 use crate::services::clipboard;
+use cosmic::iced::widget::image::Handle as ImageHandle;
 use cosmic::widget::Id;
 // use cosmic::iced::window::Id;
-use std::collections::VecDeque;
 use indexmap::IndexMap;
-use crate::services::clipboard::ClipboardEntry;
+use std::collections::{HashMap, HashSet};
+use crate::services::actions::ActionRegistry;
+use crate::services::backend::SharedBackend;
+use crate::services::clipboard::{ClipboardContent, ClipboardEntry, ClipboardSource};
+use crate::services::query::{Query, QueryError};
+use crate::services::search::SearchMatch;
+use crate::services::snippets::Snippet;
+use crate::services::storage::{HistoryStore, HistoryWriter};
+use crate::services::template::PromptSession;
+
+/// Tags offered below the tag editor.
+const MAX_TAG_SUGGESTIONS: usize = 6;
 
 /// The application model stores app-specific state used to describe its interface
 #[derive(Default)]
 pub struct AppModel {
     pub(super) core: cosmic::Core,
     pub(super) popup: Option<cosmic::iced::window::Id>,
-    pub(super) pinned_clipboard_entries: IndexMap<Id, ClipboardEntry>,
-    pub(super) clipboard_entries: IndexMap<Id, ClipboardEntry>, //<clipboard::ClipboardEntry>,
+    pub(super) entries: EntryStore,
     pub(super) search_filter: String,
+    /// Parsed form of `search_filter`; empty while the filter has an error.
+    pub(super) search_query: Query,
+    pub(super) search_error: Option<QueryError>,
+    /// Entries matching the search, computed in `update` rather than on every view.
+    pub(super) search_results: SearchResults,
     pub(super) editing_entry: Option<Id>,
-}
\ No newline at end of file
+    pub(super) store: Option<HistoryStore>,
+    /// Background saver for `store`, started on the first save. Dropping the model with it
+    /// writes any change still waiting to be saved.
+    pub(super) history_writer: Option<HistoryWriter>,
+    pub(super) config: Config,
+    pub(super) config_handler: Option<cosmic::cosmic_config::Config>,
+    pub(super) show_settings: bool,
+    pub(super) snippets: Vec<Snippet>,
+    pub(super) prompt_session: Option<PromptSession>,
+    pub(super) focused_entry: Option<Id>,
+    pub(super) expanded_entry: Option<Id>,
+    /// Decoded handle for the image shown in the preview pane, rebuilt when the target changes.
+    pub(super) preview: Option<(Id, ImageHandle)>,
+    /// Handles for the image entries' thumbnails, so rows don't rebuild them on every view.
+    pub(super) thumbnails: HashMap<Id, ImageHandle>,
+    pub(super) actions: ActionRegistry,
+    /// Entry whose overflow menu is open.
+    pub(super) open_menu: Option<Id>,
+    /// Whether the open overflow menu shows its "Copy As…" transformations.
+    pub(super) copy_as_expanded: bool,
+    /// Keep transformed copies in the history instead of only placing them on the clipboard.
+    pub(super) save_transformed: bool,
+    /// Last action failure, shown above the history until the next action.
+    pub(super) action_error: Option<String>,
+    /// Content we just placed on the clipboard ourselves and don't want recorded.
+    /// Cleared by the next clipboard change, whatever it holds.
+    pub(super) ignored_capture: Option<ClipboardContent>,
+    /// Session bus connection serving the history, used to emit signals.
+    pub(super) dbus: Option<zbus::Connection>,
+    /// System clipboard access; replaced by an in-memory one in tests.
+    pub(super) clipboard: SharedBackend,
+    /// Pinned entry being dragged to a new position.
+    pub(super) pinned_drag: Option<PinnedDrag>,
+    /// Collections whose entries are hidden while the search is empty.
+    pub(super) collapsed_collections: HashSet<String>,
+    /// Collection being renamed.
+    pub(super) editing_collection: Option<String>,
+    pub(super) collection_name_input: String,
+    /// Entry whose tag editor is open.
+    pub(super) tagging_entry: Option<Id>,
+    pub(super) tag_input: String,
+}
+
+/// Every entry matching the search, best match first with ties in history order.
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    /// Entry revision and search text the matches were computed for.
+    pub(super) computed_for: Option<(u64, String)>,
+    pub(super) matches: Vec<(Id, SearchMatch)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedDrag {
+    pub(super) id: Id,
+    /// Pinned row under the cursor, where the entry will be dropped.
+    pub(super) over: Option<Id>,
+}
+
+impl AppModel {
+    /// Pinned entries matching the search in display order: those outside any collection first,
+    /// then each expanded collection.
+    pub(super) fn visible_pinned_entries(&self) -> Vec<(&ClipboardEntry, &SearchMatch)> {
+        let mut visible = self.visible_pinned_entries_in(None);
+        for name in &self.config.collections {
+            if !self.is_collection_collapsed(name) {
+                visible.extend(self.visible_pinned_entries_in(Some(name)));
+            }
+        }
+        visible
+    }
+
+    /// Pinned entries of a collection matching the search, best match first. `None` stands for
+    /// the entries outside any collection, including ones whose collection no longer exists.
+    pub(super) fn visible_pinned_entries_in(&self, collection: Option<&str>) -> Vec<(&ClipboardEntry, &SearchMatch)> {
+        self.visible_matching(|entry| {
+            entry.pinned
+                && match collection {
+                    Some(name) => entry.collection.as_deref() == Some(name),
+                    None => !entry
+                        .collection
+                        .as_ref()
+                        .is_some_and(|name| self.config.collections.contains(name)),
+                }
+        })
+    }
+
+    /// Collapsed collections still show their matches while searching.
+    pub(super) fn is_collection_collapsed(&self, name: &str) -> bool {
+        self.collapsed_collections.contains(name) && self.search_filter.trim().is_empty()
+    }
+
+    /// Unpinned clipboard entries matching the search, best match first.
+    pub(super) fn visible_unpinned_entries(&self) -> Vec<(&ClipboardEntry, &SearchMatch)> {
+        self.visible_entries_from(ClipboardSource::Regular)
+    }
+
+    /// Unpinned primary selection entries matching the search, best match first.
+    pub(super) fn visible_primary_entries(&self) -> Vec<(&ClipboardEntry, &SearchMatch)> {
+        self.visible_entries_from(ClipboardSource::Primary)
+    }
+
+    fn visible_entries_from(&self, source: ClipboardSource) -> Vec<(&ClipboardEntry, &SearchMatch)> {
+        self.visible_matching(|entry| !entry.pinned && entry.source == source)
+    }
+
+    /// The search results `keep` accepts, in result order.
+    fn visible_matching(&self, keep: impl Fn(&ClipboardEntry) -> bool) -> Vec<(&ClipboardEntry, &SearchMatch)> {
+        self.search_results
+            .matches
+            .iter()
+            .filter_map(|(id, found)| self.entries.get(id).filter(|&entry| keep(entry)).map(|entry| (entry, found)))
+            .collect()
+    }
+
+    /// Looks up an entry by its stable id.
+    pub(super) fn entry_by_id(&self, id: &str) -> Option<&ClipboardEntry> {
+        let id = uuid::Uuid::parse_str(id).ok()?;
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Existing tags completing the word being typed in the tag editor, minus the ones the
+    /// tagged entry already has.
+    pub(super) fn tag_suggestions(&self) -> Vec<&str> {
+        let Some(entry) = self.tagging_entry.as_ref().and_then(|id| self.entries.get(id)) else {
+            return Vec::new();
+        };
+        let partial = self.tag_input.split_whitespace().last().unwrap_or_default();
+        let partial = partial.strip_prefix('#').unwrap_or(partial).to_lowercase();
+
+        let mut suggestions: Vec<&str> = self
+            .entries
+            .iter()
+            .flat_map(|other| other.tags.iter())
+            .filter(|tag| tag.starts_with(&partial) && !entry.tags.contains(tag))
+            .map(String::as_str)
+            .collect();
+        suggestions.sort_unstable();
+        suggestions.dedup();
+        suggestions.truncate(MAX_TAG_SUGGESTIONS);
+        suggestions
+    }
+
+    /// Pinned entries can only be reordered while they are all shown in their own order.
+    pub(super) fn can_reorder_pinned(&self) -> bool {
+        self.search_filter.trim().is_empty()
+    }
+
+    /// Ids of the entries shown in the popup, pinned first, in display order.
+    pub(super) fn visible_entry_ids(&self) -> Vec<Id> {
+        self.visible_pinned_entries()
+            .into_iter()
+            .chain(self.visible_unpinned_entries())
+            .chain(self.visible_primary_entries())
+            .map(|(entry, _)| entry.widget_id.clone())
+            .collect()
+    }
+}
+
+/// Every history entry, pinned or not, behind a single lookup by widget id.
+///
+/// Unpinned entries are kept newest first; pinned ones in the order they were pinned.
+#[derive(Debug, Default)]
+pub struct EntryStore {
+    entries: IndexMap<Id, ClipboardEntry>,
+    /// Bumped by every method that may change an entry or the order.
+    revision: u64,
+}
+
+impl EntryStore {
+    pub(super) fn revision(&self) -> u64 {
+        self.revision
+    }
+
+
+    pub(super) fn get(&self, id: &Id) -> Option<&ClipboardEntry> {
+        self.entries.get(id)
+    }
+
+    pub(super) fn get_mut(&mut self, id: &Id) -> Option<&mut ClipboardEntry> {
+        self.revision += 1;
+        self.entries.get_mut(id)
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries, pinned first: the order shown in the popup and saved to disk.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &ClipboardEntry> {
+        self.pinned().chain(self.unpinned())
+    }
+
+    pub(super) fn pinned(&self) -> impl Iterator<Item = &ClipboardEntry> {
+        self.entries.values().filter(|entry| entry.pinned)
+    }
+
+    pub(super) fn unpinned(&self) -> impl Iterator<Item = &ClipboardEntry> {
+        self.entries.values().filter(|entry| !entry.pinned)
+    }
+
+    pub(super) fn iter_mut(&mut self) -> impl Iterator<Item = &mut ClipboardEntry> {
+        self.revision += 1;
+        self.entries.values_mut()
+    }
+
+    /// Adds an entry after the existing ones, e.g. while restoring the saved history.
+    pub(super) fn push_back(&mut self, entry: ClipboardEntry) {
+        self.revision += 1;
+        self.entries.insert(entry.widget_id.clone(), entry);
+    }
+
+    /// Adds a newly captured entry as the most recent one.
+    pub(super) fn push_front(&mut self, entry: ClipboardEntry) {
+        self.revision += 1;
+        self.entries.insert_before(0, entry.widget_id.clone(), entry);
+    }
+
+    /// Makes the entry the most recent one.
+    pub(super) fn move_to_front(&mut self, id: &Id) {
+        self.revision += 1;
+        if let Some(index) = self.entries.get_index_of(id) {
+            self.entries.move_index(index, 0);
+        }
+    }
+
+    pub(super) fn remove(&mut self, id: &Id) -> Option<ClipboardEntry> {
+        self.revision += 1;
+        self.entries.shift_remove(id)
+    }
+
+    /// Pins or unpins the entry; returns false if there is no such entry.
+    ///
+    /// Newly pinned entries go last among the pinned ones, unpinned ones become the oldest and
+    /// leave their collection.
+    pub(super) fn set_pinned(&mut self, id: &Id, pinned: bool) -> bool {
+        self.revision += 1;
+        let Some(index) = self.entries.get_index_of(id) else {
+            return false;
+        };
+
+        let entry = &mut self.entries[index];
+        if entry.pinned != pinned {
+            entry.pinned = pinned;
+            if !pinned {
+                entry.collection = None;
+            }
+            let last = self.entries.len() - 1;
+            self.entries.move_index(index, last);
+        }
+        true
+    }
+
+    /// Position of the entry among the pinned ones, if it is pinned.
+    pub(super) fn pinned_position(&self, id: &Id) -> Option<usize> {
+        self.pinned().position(|entry| entry.widget_id == *id)
+    }
+
+    /// Moves a pinned entry to position `to` among the pinned ones, or last if `to` is past the
+    /// end; returns false if the entry is not pinned.
+    pub(super) fn move_pinned(&mut self, id: &Id, to: usize) -> bool {
+        self.revision += 1;
+        let Some(from) = self.entries.get_index_of(id).filter(|&index| self.entries[index].pinned) else {
+            return false;
+        };
+
+        let pinned_indices: Vec<usize> = self
+            .entries
+            .values()
+            .enumerate()
+            .filter(|(_, entry)| entry.pinned)
+            .map(|(index, _)| index)
+            .collect();
+        let target = pinned_indices[to.min(pinned_indices.len() - 1)];
+        self.entries.move_index(from, target);
+        true
+    }
+
+    /// The unpinned entry from `source` with this content, if one was recorded before.
+    pub(super) fn find_unpinned(&self, content: &ClipboardContent, source: ClipboardSource) -> Option<Id> {
+        self.unpinned()
+            .find(|entry| entry.content == *content && entry.source == source)
+            .map(|entry| entry.widget_id.clone())
+    }
+
+    /// Keeps the pinned entries and the unpinned ones `keep` accepts, visited newest first.
+    pub(super) fn retain_unpinned(&mut self, mut keep: impl FnMut(&ClipboardEntry) -> bool) {
+        self.revision += 1;
+        self.entries.retain(|_, entry| entry.pinned || keep(entry));
+    }
+}
@@ -0,0 +1,877 @@
+//! History behaviour driven through `update`, with an in-memory clipboard in place of Wayland.
+
+use std::sync::Arc;
+
+use super::handlers::{self, update};
+use super::{AppModel, Message};
+use crate::services::backend::{MemoryClipboard, SharedBackend};
+use crate::services::dbus;
+use crate::services::storage::HistoryStore;
+use crate::services::clipboard::{self, ClipboardContent, ClipboardSource, WriteTarget};
+use crate::services::transform::Transform;
+use cosmic::widget::Id;
+
+fn app_with_fake_clipboard() -> (AppModel, Arc<MemoryClipboard>) {
+    let fake = Arc::new(MemoryClipboard::new());
+    let app = AppModel {
+        clipboard: SharedBackend::new(fake.clone()),
+        ..Default::default()
+    };
+    (app, fake)
+}
+
+/// Reads the selection the way the clipboard subscription does and hands it to the model.
+fn capture(app: &mut AppModel, fake: &MemoryClipboard, source: ClipboardSource) {
+    if let Some(entry) = clipboard::read_clipboard_entry(fake, &app.config, source) {
+        _ = update(app, Message::ClipboardChanged(entry));
+    }
+}
+
+fn copy_text(app: &mut AppModel, fake: &MemoryClipboard, text: &str) {
+    fake.offer_text(ClipboardSource::Regular, text);
+    capture(app, fake, ClipboardSource::Regular);
+}
+
+fn texts<'a>(entries: impl Iterator<Item = &'a clipboard::ClipboardEntry>) -> Vec<&'a str> {
+    entries
+        .map(|entry| match &entry.content {
+            ClipboardContent::Text(text) => text.as_str(),
+            ClipboardContent::Image { .. } => "<image>",
+        })
+        .collect()
+}
+
+fn history(app: &AppModel) -> Vec<&str> {
+    texts(app.entries.unpinned())
+}
+
+fn pinned(app: &AppModel) -> Vec<&str> {
+    texts(app.entries.pinned())
+}
+
+fn id_of(app: &AppModel, text: &str) -> Id {
+    app.entries
+        .iter()
+        .find(|entry| entry.content == ClipboardContent::Text(text.to_string()))
+        .map(|entry| entry.widget_id.clone())
+        .expect("entry should exist")
+}
+
+#[test]
+fn new_copies_are_recorded_newest_first() {
+    let (mut app, fake) = app_with_fake_clipboard();
+
+    copy_text(&mut app, &fake, "one");
+    copy_text(&mut app, &fake, "two");
+    copy_text(&mut app, &fake, "three");
+
+    assert_eq!(history(&app), ["three", "two", "one"]);
+}
+
+#[test]
+fn repeated_copy_is_deduplicated_and_moved_to_top() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "one");
+    copy_text(&mut app, &fake, "two");
+    let id = id_of(&app, "one");
+
+    copy_text(&mut app, &fake, "one");
+
+    assert_eq!(history(&app), ["one", "two"]);
+    assert_eq!(id_of(&app, "one"), id, "the existing entry is reused");
+}
+
+#[test]
+fn same_text_in_each_selection_is_kept_apart() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "shared");
+    fake.offer_text(ClipboardSource::Primary, "shared");
+    capture(&mut app, &fake, ClipboardSource::Primary);
+
+    assert_eq!(history(&app), ["shared", "shared"]);
+    assert_eq!(app.visible_primary_entries().len(), 1);
+}
+
+#[test]
+fn oldest_entries_are_evicted_at_the_history_limit() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_history = 3;
+
+    for text in ["one", "two", "three", "four", "five"] {
+        copy_text(&mut app, &fake, text);
+    }
+
+    assert_eq!(history(&app), ["five", "four", "three"]);
+}
+
+#[test]
+fn a_zero_history_limit_still_keeps_the_latest_copy() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_history = 0;
+
+    for text in ["one", "two"] {
+        copy_text(&mut app, &fake, text);
+    }
+
+    assert_eq!(history(&app), ["two"]);
+}
+
+#[test]
+fn primary_selection_has_its_own_limit() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_history = 2;
+    app.config.max_primary_history = 1;
+
+    copy_text(&mut app, &fake, "regular");
+    for text in ["selected one", "selected two"] {
+        fake.offer_text(ClipboardSource::Primary, text);
+        capture(&mut app, &fake, ClipboardSource::Primary);
+    }
+
+    assert_eq!(history(&app), ["selected two", "regular"]);
+}
+
+#[test]
+fn pinned_entries_leave_the_history_and_are_never_evicted() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_history = 2;
+    copy_text(&mut app, &fake, "keep me");
+
+    _ = update(&mut app, Message::TogglePinEntry(id_of(&app, "keep me")));
+    for text in ["one", "two", "three"] {
+        copy_text(&mut app, &fake, text);
+    }
+
+    assert_eq!(pinned(&app), ["keep me"]);
+    assert_eq!(history(&app), ["three", "two"]);
+}
+
+#[test]
+fn unpinning_returns_the_entry_to_the_history() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "one");
+    let id = id_of(&app, "one");
+
+    _ = update(&mut app, Message::TogglePinEntry(id.clone()));
+    _ = update(&mut app, Message::TogglePinEntry(id));
+
+    assert!(pinned(&app).is_empty());
+    assert_eq!(history(&app), ["one"]);
+}
+
+#[test]
+fn removed_entries_are_gone() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "one");
+    copy_text(&mut app, &fake, "two");
+
+    _ = update(&mut app, Message::RemoveHistory(id_of(&app, "one")));
+
+    assert_eq!(history(&app), ["two"]);
+}
+
+#[test]
+fn clearing_keeps_pinned_entries() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "pinned");
+    copy_text(&mut app, &fake, "other");
+    _ = update(&mut app, Message::TogglePinEntry(id_of(&app, "pinned")));
+
+    _ = update(&mut app, Message::ClearHistory);
+
+    assert!(history(&app).is_empty());
+    assert_eq!(pinned(&app), ["pinned"]);
+}
+
+#[test]
+fn copying_from_history_offers_every_representation() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    fake.offer(
+        ClipboardSource::Regular,
+        vec![
+            ("text/plain;charset=utf-8".to_string(), b"bold".to_vec()),
+            ("text/html".to_string(), b"<b>bold</b>".to_vec()),
+        ],
+    );
+    capture(&mut app, &fake, ClipboardSource::Regular);
+    copy_text(&mut app, &fake, "something else");
+
+    _ = update(&mut app, Message::CopyFromHistory(id_of(&app, "bold")));
+
+    let offered = fake.contents(ClipboardSource::Regular);
+    assert_eq!(fake.text(ClipboardSource::Regular).as_deref(), Some("bold"));
+    assert!(offered.contains(&("text/html".to_string(), b"<b>bold</b>".to_vec())));
+}
+
+#[test]
+fn concealed_selections_are_not_recorded() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    fake.offer(
+        ClipboardSource::Regular,
+        vec![
+            ("text/plain;charset=utf-8".to_string(), b"hunter2".to_vec()),
+            ("application/x-nspasteboard-concealed-type".to_string(), Vec::new()),
+        ],
+    );
+
+    capture(&mut app, &fake, ClipboardSource::Regular);
+
+    assert!(history(&app).is_empty());
+}
+
+#[test]
+fn kept_alive_selections_are_not_recorded_twice() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "one");
+    let entry = app.entries.get(&id_of(&app, "one")).cloned().expect("entry should exist");
+
+    assert!(clipboard::keep_clipboard_alive(&*fake, &entry));
+
+    assert_eq!(fake.text(ClipboardSource::Regular).as_deref(), Some("one"));
+    assert!(clipboard::read_clipboard_entry(&*fake, &app.config, ClipboardSource::Regular).is_none());
+}
+
+/// Copies `text`, pins it and copies something else on top, so it is only reachable pinned.
+fn pinned_entry(app: &mut AppModel, fake: &MemoryClipboard, text: &str) -> Id {
+    copy_text(app, fake, text);
+    let id = id_of(app, text);
+    _ = update(app, Message::TogglePinEntry(id.clone()));
+    copy_text(app, fake, "something else");
+    id
+}
+
+#[test]
+fn pinned_entries_can_be_copied() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "pinned");
+
+    _ = update(&mut app, Message::CopyFromHistory(id));
+
+    assert_eq!(fake.text(ClipboardSource::Regular).as_deref(), Some("pinned"));
+}
+
+#[test]
+fn pinned_entries_can_be_copied_to_the_primary_selection() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "pinned");
+
+    _ = update(&mut app, Message::CopyToSelection(id, WriteTarget::Primary));
+
+    assert_eq!(fake.text(ClipboardSource::Primary).as_deref(), Some("pinned"));
+}
+
+#[test]
+fn pinned_entries_can_be_copied_transformed() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "pinned");
+
+    _ = update(&mut app, Message::CopyTransformed(id, Transform::Uppercase));
+
+    assert_eq!(fake.text(ClipboardSource::Regular).as_deref(), Some("PINNED"));
+}
+
+#[test]
+fn pinned_entries_can_be_renamed() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "pinned");
+
+    _ = update(&mut app, Message::EditToggled(id.clone()));
+    _ = update(&mut app, Message::EditableInputChanged("Renamed".to_string()));
+    _ = update(&mut app, Message::EditableInputSubmitted("Renamed".to_string()));
+
+    let entry = app.entries.get(&id).expect("entry should exist");
+    assert_eq!(entry.title, "Renamed");
+    assert!(entry.pinned);
+    assert_eq!(app.editing_entry, None);
+}
+
+#[test]
+fn pinned_entries_can_be_removed() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "pinned");
+
+    _ = update(&mut app, Message::RemoveHistory(id.clone()));
+
+    assert!(app.entries.get(&id).is_none());
+    assert!(pinned(&app).is_empty());
+    assert_eq!(history(&app), ["something else"]);
+}
+
+#[test]
+fn pinned_entries_can_be_hidden() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "pinned");
+
+    _ = update(&mut app, Message::ToggleSensitive(id.clone()));
+
+    assert!(app.entries.get(&id).is_some_and(|entry| entry.sensitive));
+}
+
+#[test]
+fn pinned_entries_keep_the_order_they_were_pinned_in() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    for text in ["one", "two", "three"] {
+        copy_text(&mut app, &fake, text);
+    }
+
+    for text in ["two", "three", "one"] {
+        _ = update(&mut app, Message::TogglePinEntry(id_of(&app, text)));
+    }
+
+    assert_eq!(pinned(&app), ["two", "three", "one"]);
+}
+
+#[test]
+fn pinned_entries_can_be_moved() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    for text in ["one", "two", "three"] {
+        pinned_entry(&mut app, &fake, text);
+    }
+
+    _ = update(&mut app, Message::MovePinned { id: id_of(&app, "three"), to: 0 });
+    assert_eq!(pinned(&app), ["three", "one", "two"]);
+
+    _ = update(&mut app, Message::MovePinned { id: id_of(&app, "three"), to: 5 });
+    assert_eq!(pinned(&app), ["one", "two", "three"]);
+}
+
+#[test]
+fn dropping_a_dragged_pinned_entry_moves_it() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    for text in ["one", "two", "three"] {
+        pinned_entry(&mut app, &fake, text);
+    }
+    let (one, three) = (id_of(&app, "one"), id_of(&app, "three"));
+
+    _ = update(&mut app, Message::PinnedDragStarted(three.clone()));
+    _ = update(&mut app, Message::PinnedDragEntered(one.clone()));
+    _ = update(&mut app, Message::PinnedDropped(one));
+    // The drop is forwarded as a `MovePinned` task, which the runtime would deliver next.
+    _ = update(&mut app, Message::MovePinned { id: three, to: 0 });
+
+    assert_eq!(pinned(&app), ["three", "one", "two"]);
+    assert!(app.pinned_drag.is_none());
+}
+
+/// Loads what `store` holds into a fresh model, as the applet does on start.
+fn restored_from(store: HistoryStore) -> AppModel {
+    let mut restored = AppModel {
+        store: Some(store),
+        ..Default::default()
+    };
+    handlers::restore_history(&mut restored);
+    restored
+}
+
+#[test]
+fn moved_pinned_entries_keep_their_order_after_a_restart() {
+    let root = std::env::temp_dir().join(format!("clippy-land-test-{}", uuid::Uuid::new_v4()));
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.store = Some(HistoryStore::open(root.clone()));
+    for text in ["one", "two"] {
+        pinned_entry(&mut app, &fake, text);
+    }
+
+    _ = update(&mut app, Message::MovePinned { id: id_of(&app, "two"), to: 0 });
+    drop(app);
+
+    let restored = restored_from(HistoryStore::open(root.clone()));
+    _ = std::fs::remove_dir_all(&root);
+
+    assert_eq!(pinned(&restored), ["two", "one"]);
+    assert_eq!(history(&restored), ["something else"]);
+}
+
+#[test]
+fn changes_right_before_exit_are_saved() {
+    let root = std::env::temp_dir().join(format!("clippy-land-test-{}", uuid::Uuid::new_v4()));
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.store = Some(HistoryStore::open(root.clone()));
+
+    copy_text(&mut app, &fake, "first");
+    copy_text(&mut app, &fake, "second");
+    _ = update(&mut app, Message::TogglePinEntry(id_of(&app, "first")));
+    // Well within the save delay, so only dropping the model can have written these.
+    drop(app);
+
+    let restored = restored_from(HistoryStore::open(root.clone()));
+    _ = std::fs::remove_dir_all(&root);
+
+    assert_eq!(pinned(&restored), ["first"]);
+    assert_eq!(history(&restored), ["second"]);
+}
+
+#[test]
+fn saved_history_is_private_to_the_user() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = std::env::temp_dir().join(format!("clippy-land-test-{}", uuid::Uuid::new_v4()));
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.store = Some(HistoryStore::open(root.clone()));
+    copy_text(&mut app, &fake, "hunter2");
+    drop(app);
+
+    let mode = |path: std::path::PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    let (root_mode, history_mode) = (mode(root.clone()), mode(root.join("history.json")));
+    _ = std::fs::remove_dir_all(&root);
+
+    assert_eq!(root_mode, 0o700);
+    assert_eq!(history_mode, 0o600);
+}
+
+fn collection_of(app: &AppModel, text: &str) -> Option<String> {
+    app.entries.get(&id_of(app, text)).and_then(|entry| entry.collection.clone())
+}
+
+#[test]
+fn created_collections_get_unique_names() {
+    let (mut app, _fake) = app_with_fake_clipboard();
+
+    _ = update(&mut app, Message::CreateCollection("Work".into()));
+    _ = update(&mut app, Message::CreateCollection("work".into()));
+    _ = update(&mut app, Message::CreateCollection("  ".into()));
+
+    assert_eq!(app.config.collections, ["Work", "work 2"]);
+    assert_eq!(app.editing_collection.as_deref(), Some("work 2"));
+}
+
+#[test]
+fn moving_an_entry_into_a_collection_pins_it() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "notes");
+    _ = update(&mut app, Message::CreateCollection("Work".into()));
+
+    _ = update(&mut app, Message::SetEntryCollection(id_of(&app, "notes"), Some("Work".into())));
+    assert_eq!(pinned(&app), ["notes"]);
+    assert_eq!(collection_of(&app, "notes").as_deref(), Some("Work"));
+
+    _ = update(&mut app, Message::SetEntryCollection(id_of(&app, "notes"), Some("Missing".into())));
+    assert_eq!(collection_of(&app, "notes").as_deref(), Some("Work"));
+
+    _ = update(&mut app, Message::TogglePinEntry(id_of(&app, "notes")));
+    assert_eq!(history(&app), ["notes"]);
+    assert_eq!(collection_of(&app, "notes"), None);
+}
+
+#[test]
+fn renamed_collections_keep_their_entries() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "notes");
+    _ = update(&mut app, Message::CreateCollection("Work".into()));
+    _ = update(&mut app, Message::CreateCollection("Home".into()));
+    _ = update(&mut app, Message::SetEntryCollection(id, Some("Work".into())));
+
+    _ = update(&mut app, Message::RenameCollection { from: "Work".into(), to: "home".into() });
+    assert_eq!(app.config.collections, ["Work", "Home"]);
+
+    _ = update(&mut app, Message::RenameCollection { from: "Work".into(), to: "Side project".into() });
+    assert_eq!(app.config.collections, ["Side project", "Home"]);
+    assert_eq!(collection_of(&app, "notes").as_deref(), Some("Side project"));
+}
+
+#[test]
+fn deleting_a_collection_keeps_its_entries_pinned() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "notes");
+    _ = update(&mut app, Message::CreateCollection("Work".into()));
+    _ = update(&mut app, Message::SetEntryCollection(id, Some("Work".into())));
+
+    _ = update(&mut app, Message::DeleteCollection("Work".into()));
+
+    assert!(app.config.collections.is_empty());
+    assert_eq!(pinned(&app), ["notes"]);
+    assert_eq!(collection_of(&app, "notes"), None);
+}
+
+#[test]
+fn search_can_be_scoped_to_a_collection() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    for text in ["report", "recipe"] {
+        pinned_entry(&mut app, &fake, text);
+    }
+    _ = update(&mut app, Message::CreateCollection("Side project".into()));
+    _ = update(&mut app, Message::SetEntryCollection(id_of(&app, "report"), Some("Side project".into())));
+
+    _ = update(&mut app, Message::SearchInputChanged("in:side-project".into()));
+
+    let visible: Vec<_> = app.visible_pinned_entries().into_iter().map(|(entry, _)| entry.widget_id.clone()).collect();
+    assert_eq!(visible, [id_of(&app, "report")]);
+    assert!(app.visible_unpinned_entries().is_empty());
+}
+
+#[test]
+fn collapsed_collections_hide_their_entries_until_searched() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "notes");
+    _ = update(&mut app, Message::CreateCollection("Work".into()));
+    _ = update(&mut app, Message::SetEntryCollection(id.clone(), Some("Work".into())));
+
+    _ = update(&mut app, Message::ToggleCollectionCollapsed("Work".into()));
+    assert!(app.visible_pinned_entries().is_empty());
+
+    _ = update(&mut app, Message::SearchInputChanged("notes".into()));
+    assert_eq!(app.visible_pinned_entries().len(), 1);
+}
+
+#[test]
+fn search_results_follow_the_entries() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "report");
+    _ = update(&mut app, Message::SearchInputChanged("rep".into()));
+    assert_eq!(app.visible_unpinned_entries().len(), 1);
+
+    copy_text(&mut app, &fake, "second report");
+    copy_text(&mut app, &fake, "unrelated");
+    let visible: Vec<_> = app.visible_unpinned_entries().into_iter().map(|(entry, _)| entry.widget_id.clone()).collect();
+    assert_eq!(visible, [id_of(&app, "second report"), id_of(&app, "report")]);
+
+    _ = update(&mut app, Message::RemoveHistory(id_of(&app, "report")));
+    assert_eq!(app.visible_unpinned_entries().len(), 1);
+}
+
+#[test]
+fn only_the_start_of_long_entries_is_searched() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, &format!("{}needle", "x".repeat(64 * 1024)));
+
+    _ = update(&mut app, Message::SearchInputChanged("needle".into()));
+    assert!(app.visible_unpinned_entries().is_empty());
+}
+
+fn tags_of(app: &AppModel, text: &str) -> Vec<String> {
+    app.entries.get(&id_of(app, text)).map(|entry| entry.tags.clone()).unwrap_or_default()
+}
+
+#[test]
+fn tags_are_normalized_and_not_repeated() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "select 1");
+
+    _ = update(&mut app, Message::ToggleTagEditor(id_of(&app, "select 1")));
+    _ = update(&mut app, Message::TagInputSubmitted("#SQL prod, sql #".into()));
+
+    assert_eq!(tags_of(&app, "select 1"), ["sql", "prod"]);
+
+    _ = update(&mut app, Message::RemoveTag(id_of(&app, "select 1"), "sql".into()));
+    assert_eq!(tags_of(&app, "select 1"), ["prod"]);
+}
+
+#[test]
+fn tags_survive_copying_the_same_content_again() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "select 1");
+    _ = update(&mut app, Message::AddTag(id_of(&app, "select 1"), "sql".into()));
+    copy_text(&mut app, &fake, "other");
+
+    copy_text(&mut app, &fake, "select 1");
+
+    assert_eq!(history(&app), ["select 1", "other"]);
+    assert_eq!(tags_of(&app, "select 1"), ["sql"]);
+}
+
+#[test]
+fn copying_a_tagged_pinned_entry_keeps_its_tags() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    let id = pinned_entry(&mut app, &fake, "select 1");
+    _ = update(&mut app, Message::AddTag(id, "sql".into()));
+
+    copy_text(&mut app, &fake, "select 1");
+
+    let copy = app.entries.unpinned().next().expect("copy should be recorded");
+    assert_eq!(copy.tags, ["sql"]);
+}
+
+#[test]
+fn tags_can_be_searched() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    for text in ["select 1", "deploy"] {
+        copy_text(&mut app, &fake, text);
+    }
+    _ = update(&mut app, Message::AddTag(id_of(&app, "select 1"), "sql".into()));
+    _ = update(&mut app, Message::AddTag(id_of(&app, "deploy"), "prod".into()));
+
+    for query in ["#sql", "tag:SQL", "-#prod"] {
+        _ = update(&mut app, Message::SearchInputChanged(query.into()));
+        let visible: Vec<_> = app.visible_unpinned_entries().into_iter().map(|(entry, _)| entry.widget_id.clone()).collect();
+        assert_eq!(visible, [id_of(&app, "select 1")], "{query}");
+    }
+}
+
+#[test]
+fn existing_tags_are_suggested() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    for text in ["one", "two"] {
+        copy_text(&mut app, &fake, text);
+    }
+    for tag in ["prod", "private", "sql"] {
+        _ = update(&mut app, Message::AddTag(id_of(&app, "one"), tag.into()));
+    }
+    _ = update(&mut app, Message::AddTag(id_of(&app, "two"), "private".into()));
+
+    _ = update(&mut app, Message::ToggleTagEditor(id_of(&app, "two")));
+    _ = update(&mut app, Message::TagInputChanged("#p".into()));
+
+    assert_eq!(app.tag_suggestions(), ["prod"]);
+}
+
+/// Pretends the entry was last copied `secs` seconds ago.
+fn age_entry(app: &mut AppModel, text: &str, secs: u64) {
+    let id = id_of(app, text);
+    if let Some(entry) = app.entries.get_mut(&id) {
+        entry.last_copied_at = std::time::SystemTime::now() - std::time::Duration::from_secs(secs);
+    }
+}
+
+#[test]
+fn entries_expire_after_the_maximum_age() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_age_hours = 24;
+    for text in ["old", "pinned old", "new"] {
+        copy_text(&mut app, &fake, text);
+    }
+    _ = update(&mut app, Message::TogglePinEntry(id_of(&app, "pinned old")));
+    age_entry(&mut app, "old", 25 * 60 * 60);
+    age_entry(&mut app, "pinned old", 25 * 60 * 60);
+
+    _ = update(&mut app, Message::EnforceRetention);
+
+    assert_eq!(history(&app), ["new"]);
+    assert_eq!(pinned(&app), ["pinned old"]);
+}
+
+#[test]
+fn hidden_entries_expire_on_their_own_schedule() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.sensitive_expiry_secs = 30;
+    for text in ["secret", "visible"] {
+        copy_text(&mut app, &fake, text);
+        age_entry(&mut app, text, 60);
+    }
+    _ = update(&mut app, Message::ToggleSensitive(id_of(&app, "secret")));
+
+    _ = update(&mut app, Message::EnforceRetention);
+
+    assert_eq!(history(&app), ["visible"]);
+}
+
+#[test]
+fn text_and_images_have_separate_limits() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_text_entries = 2;
+    app.config.max_image_entries = 1;
+    for text in ["one", "two", "three"] {
+        copy_text(&mut app, &fake, text);
+    }
+    let text_id = id_of(&app, "one");
+    _ = update(&mut app, Message::TogglePinEntry(text_id));
+
+    copy_text(&mut app, &fake, "four");
+    assert_eq!(history(&app), ["four", "three"]);
+    assert_eq!(pinned(&app), ["one"]);
+
+    // Images only push out older images, and text only older text.
+    copy_image(&mut app, "first shot", 1);
+    copy_image(&mut app, "second shot", 2);
+    assert_eq!(unpinned_titles(&app), ["second shot", "four", "three"]);
+
+    copy_text(&mut app, &fake, "five");
+    assert_eq!(unpinned_titles(&app), ["five", "second shot", "four"]);
+}
+
+fn copy_image(app: &mut AppModel, title: &str, seed: u8) {
+    let image = ClipboardContent::image("image/png".into(), vec![0x89, b'P', b'N', b'G', seed], 40);
+    _ = update(app, Message::ClipboardChanged(clipboard::ClipboardEntry::new(title.into(), image)));
+}
+
+fn unpinned_titles(app: &AppModel) -> Vec<&str> {
+    app.entries.unpinned().map(|entry| entry.title.as_str()).collect()
+}
+
+#[test]
+fn the_size_budget_keeps_the_newest_entries() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    app.config.max_history_bytes = 10;
+    for text in ["aaaa", "bbbb", "cccc"] {
+        copy_text(&mut app, &fake, text);
+    }
+
+    assert_eq!(history(&app), ["cccc", "bbbb"]);
+}
+
+#[test]
+fn retention_is_off_by_default() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "old");
+    age_entry(&mut app, "old", 365 * 24 * 60 * 60);
+
+    _ = update(&mut app, Message::EnforceRetention);
+
+    assert_eq!(history(&app), ["old"]);
+}
+
+#[test]
+fn hidden_images_are_never_previewed() {
+    let (mut app, _fake) = app_with_fake_clipboard();
+    let image = ClipboardContent::image("image/png".into(), vec![0x89, b'P', b'N', b'G'], 40);
+    let entry = clipboard::ClipboardEntry::new("screenshot".into(), image);
+    let id = entry.widget_id.clone();
+    _ = update(&mut app, Message::ClipboardChanged(entry));
+
+    _ = update(&mut app, Message::TogglePreview(id.clone()));
+    assert!(app.preview.is_some());
+
+    _ = update(&mut app, Message::ToggleSensitive(id.clone()));
+    assert!(app.preview.is_none());
+
+    app.expanded_entry = None;
+    app.focused_entry = Some(id);
+    _ = update(&mut app, Message::CloseEntryMenu);
+    assert!(app.preview.is_none());
+}
+
+#[test]
+fn transformed_copies_are_only_ignored_once() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "hello");
+
+    _ = update(&mut app, Message::CopyTransformed(id_of(&app, "hello"), Transform::Uppercase));
+    capture(&mut app, &fake, ClipboardSource::Regular);
+    assert_eq!(history(&app), ["hello"]);
+
+    copy_text(&mut app, &fake, "HELLO");
+    assert_eq!(history(&app), ["HELLO", "hello"]);
+}
+
+#[test]
+fn a_missed_transformed_copy_does_not_hide_later_copies() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "hello");
+
+    // The watcher never reports our own write; the next change is a different copy.
+    _ = update(&mut app, Message::CopyTransformed(id_of(&app, "hello"), Transform::Uppercase));
+    copy_text(&mut app, &fake, "other");
+    copy_text(&mut app, &fake, "HELLO");
+
+    assert_eq!(history(&app), ["HELLO", "other", "hello"]);
+}
+
+/// Runs `client` against the D-Bus interface served on a private peer-to-peer connection, with
+/// every forwarded request handled by `update` like the applet does.
+async fn with_dbus_peer<F, Fut>(app: &mut AppModel, client: F)
+where
+    F: FnOnce(dbus::HistoryProxy<'static>) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let (server_stream, client_stream) = tokio::net::UnixStream::pair().expect("socket pair");
+    let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+    let server = zbus::connection::Builder::unix_stream(server_stream)
+        .server(zbus::Guid::generate())
+        .expect("server guid")
+        .p2p();
+    let peer = zbus::connection::Builder::unix_stream(client_stream).p2p().build();
+
+    let (server, peer) = tokio::join!(dbus::serve_on(server, tx), peer);
+    let (_server, peer) = (server.expect("server connection"), peer.expect("client connection"));
+    let proxy = dbus::HistoryProxy::new(&peer).await.expect("proxy");
+
+    let client = client(proxy);
+    tokio::pin!(client);
+    loop {
+        tokio::select! {
+            () = &mut client => break,
+            Some(request) = requests.recv() => {
+                _ = update(app, Message::Dbus(request));
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn dbus_calls_round_trip_through_update() {
+    let (mut app, _fake) = app_with_fake_clipboard();
+
+    with_dbus_peer(&mut app, |proxy| async move {
+        let id = proxy.add("", "from the bus").await.expect("add");
+        let listed = proxy.list().await.expect("list");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, id);
+        assert_eq!(listed[0].title, "from the bus");
+        assert!(!listed[0].pinned);
+
+        proxy.pin(&id).await.expect("pin");
+        assert!(proxy.list().await.expect("list")[0].pinned);
+        assert!(proxy.pin("not-an-id").await.is_err());
+    })
+    .await;
+
+    assert_eq!(pinned(&app), ["from the bus"]);
+}
+
+#[tokio::test]
+async fn dbus_rejects_blank_text() {
+    let (mut app, _fake) = app_with_fake_clipboard();
+
+    with_dbus_peer(&mut app, |proxy| async move {
+        for text in ["", "  \n\t"] {
+            let err = proxy.add("title", text).await.expect_err("blank text");
+            assert!(matches!(zbus::fdo::Error::from(err), zbus::fdo::Error::InvalidArgs(_)));
+        }
+    })
+    .await;
+
+    assert!(app.entries.is_empty());
+}
+
+#[tokio::test]
+async fn dbus_cannot_copy_hidden_entries() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "hunter2");
+    _ = update(&mut app, Message::ToggleSensitive(id_of(&app, "hunter2")));
+    fake.offer_text(ClipboardSource::Regular, "something else");
+    let id = app.entries.iter().next().expect("entry").id.to_string();
+
+    with_dbus_peer(&mut app, |proxy| async move {
+        let err = proxy.copy(&id).await.expect_err("hidden entry");
+        assert!(matches!(zbus::fdo::Error::from(err), zbus::fdo::Error::InvalidArgs(_)));
+        assert!(proxy.get(&id).await.is_err());
+    })
+    .await;
+
+    assert_eq!(fake.text(ClipboardSource::Regular).as_deref(), Some("something else"));
+}
+
+#[tokio::test]
+async fn dbus_add_leaves_the_ignored_capture_alone() {
+    let (mut app, fake) = app_with_fake_clipboard();
+    copy_text(&mut app, &fake, "hello");
+    _ = update(&mut app, Message::CopyTransformed(id_of(&app, "hello"), Transform::Uppercase));
+
+    with_dbus_peer(&mut app, |proxy| async move {
+        proxy.add("", "HELLO").await.expect("add");
+    })
+    .await;
+    assert_eq!(history(&app), ["HELLO", "hello"]);
+    assert!(app.ignored_capture.is_some());
+
+    // Our own write is still recognised when the watcher reports it.
+    capture(&mut app, &fake, ClipboardSource::Regular);
+    assert!(app.ignored_capture.is_none());
+    assert_eq!(history(&app), ["HELLO", "hello"]);
+}
+
+#[test]
+fn thumbnail_handles_follow_the_image_entries() {
+    let (mut app, _fake) = app_with_fake_clipboard();
+    let mut image = ClipboardContent::image("image/png".into(), vec![1, 2, 3], 40);
+    if let ClipboardContent::Image { thumbnail_png, .. } = &mut image {
+        *thumbnail_png = Some(vec![0x89, b'P', b'N', b'G']);
+    }
+    let entry = clipboard::ClipboardEntry::new("screenshot".into(), image);
+    let id = entry.widget_id.clone();
+
+    _ = update(&mut app, Message::ClipboardChanged(entry));
+    assert!(app.thumbnails.contains_key(&id));
+
+    _ = update(&mut app, Message::RemoveHistory(id.clone()));
+    assert!(!app.thumbnails.contains_key(&id));
+}
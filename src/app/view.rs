@@ -1,15 +1,22 @@
 use super::{AppModel, Message};
+use crate::config::MIN_HISTORY;
 use crate::fl;
 use crate::services::clipboard;
 use cosmic::applet::menu_button;
-use cosmic::iced::widget::image::Handle as ImageHandle;
 use cosmic::iced::{Alignment, Length, window::Id, Padding, Pixels};
 use cosmic::iced_core::text::Wrapping;
 use cosmic::widget::{row, column, Column};
 use cosmic::prelude::*;
 use cosmic::widget::{self, text_input, ListColumn, Widget};
 use cosmic::widget::icon::Handle;
-use crate::services::clipboard::ClipboardEntry;
+use crate::services::clipboard::{ClipboardEntry, ContentKind, WriteTarget};
+use crate::services::search::{self, SearchMatch};
+use crate::services::template::PromptSession;
+use crate::services::transform::Transform;
+use cosmic::iced::mouse;
+use cosmic::iced::widget::{mouse_area, rich_text, span, text::Span};
+use std::ops::Range;
+use super::handlers::{COLLECTION_INPUT_ID, TAG_INPUT_ID, TEMPLATE_INPUT_ID};
 
 pub fn view(app: &AppModel) -> Element<'_, Message> {
     app.core
@@ -19,12 +26,43 @@ pub fn view(app: &AppModel) -> Element<'_, Message> {
         .into()
 }
 
-pub fn view_clipboard_entry<'a>(app: &AppModel, entry: &'a ClipboardEntry) -> Element<'a, Message> {
+const MASKED_TITLE: &str = "••••••••";
+const PREVIEW_HEIGHT: f32 = 240.0;
+
+/// Renders `title` with the matched `ranges` in bold.
+fn highlighted_title<'a>(title: &'a str, ranges: &[Range<usize>]) -> Element<'a, Message> {
+    if ranges.is_empty() {
+        return widget::text::body(title).into();
+    }
+
+    let mut spans: Vec<Span<'a>> = Vec::new();
+    let mut cursor = 0;
+    for range in ranges {
+        if range.start > cursor {
+            spans.push(span(&title[cursor..range.start]));
+        }
+        spans.push(span(&title[range.clone()]).font(cosmic::font::bold()));
+        cursor = range.end;
+    }
+    if cursor < title.len() {
+        spans.push(span(&title[cursor..]));
+    }
+
+    rich_text(spans).into()
+}
+
+pub fn view_clipboard_entry<'a>(app: &AppModel, entry: &'a ClipboardEntry, highlights: &[Range<usize>]) -> Element<'a, Message> {
+    if entry.sensitive {
+        return menu_button(widget::text::body(MASKED_TITLE))
+            .on_press(Message::CopyFromHistory(entry.widget_id.clone()))
+            .into();
+    }
+
     match entry.content {
         clipboard::ClipboardContent::Text(ref text) => {
             let is_editable = app.editing_entry == Some(entry.widget_id.clone());
             if !is_editable {
-                menu_button(widget::text::body(&entry.title))
+                menu_button(highlighted_title(&entry.title, highlights))
                     .on_press(Message::CopyFromHistory(entry.widget_id.clone()))
                     .into()
             } else {
@@ -38,53 +76,207 @@ pub fn view_clipboard_entry<'a>(app: &AppModel, entry: &'a ClipboardEntry) -> El
                 inline_input.into()
             }
         },
-        clipboard::ClipboardContent::Image { ref mime, ref bytes, .. } => {
-            // widget::text::body(fl!("clipboard-image")).into()
+        clipboard::ClipboardContent::Image { ref mime, ref bytes, width, height, .. } => {
             let is_editable = app.editing_entry == Some(entry.widget_id.clone());
             if !is_editable {
-                menu_button(widget::text::body(&entry.title))
+                let thumbnail = app
+                    .thumbnails
+                    .get(&entry.widget_id)
+                    .filter(|_| app.config.show_thumbnails)
+                    .map(|handle| widget::image(handle.clone()));
+
+                let details = widget::column()
+                    .push(highlighted_title(&entry.title, highlights))
+                    .push(widget::text::caption(describe_image(mime, bytes.len(), width, height)));
+
+                let content = widget::row()
+                    .spacing(8)
+                    .align_y(Alignment::Center)
+                    .push_maybe(thumbnail)
+                    .push(details);
+
+                menu_button(content)
                     .on_press(Message::CopyFromHistory(entry.widget_id.clone()))
                     .into()
             } else {
-                let input_id = widget::Id::unique();
-                let mut inline_input = widget::inline_input("Name entry", &entry.title)
+                widget::inline_input("Name entry", &entry.title)
                     .on_input(Message::EditableInputChanged)
                     .on_submit(Message::EditableInputSubmitted)
                     .id(entry.widget_id.clone())
-                    .select_on_focus(true);
+                    .select_on_focus(true)
+                    .into()
+            }
+        }
+    }
+}
 
-                inline_input.into()
+/// e.g. `image/png · 1920×1080 · 245 KB`
+fn describe_image(mime: &str, len: usize, width: u32, height: u32) -> String {
+    let size_kb = (len.saturating_add(1023)) / 1024;
+    if width == 0 || height == 0 {
+        format!("{mime} · {size_kb} KB")
+    } else {
+        format!("{mime} · {width}×{height} · {size_kb} KB")
+    }
+}
+
+/// Large preview of the expanded image entry, or the focused one.
+fn view_image_preview(app: &AppModel) -> Option<Element<'_, Message>> {
+    let (id, handle) = app.preview.as_ref()?;
+    let entry = app.entries.get(id).filter(|entry| !entry.sensitive)?;
+
+    let clipboard::ClipboardContent::Image { mime, bytes, width, height, .. } = &entry.content else {
+        return None;
+    };
+
+    let preview = widget::column()
+        .spacing(4)
+        .align_x(Alignment::Center)
+        .push(
+            widget::image(handle.clone())
+                .content_fit(cosmic::iced::ContentFit::Contain)
+                .height(Length::Fixed(PREVIEW_HEIGHT)),
+        )
+        .push(widget::text::caption(describe_image(mime, bytes.len(), *width, *height)));
+
+    Some(
+        widget::container(preview)
+            .center_x(Length::Fill)
+            .padding(Padding::from(8))
+            .into(),
+    )
+}
+
+fn kind_icon_name(kind: ContentKind) -> &'static str {
+    match kind {
+        ContentKind::PlainText => "text-x-generic-symbolic",
+        ContentKind::Url => "web-browser-symbolic",
+        ContentKind::Email => "mail-send-symbolic",
+        ContentKind::FilePath => "folder-symbolic",
+        ContentKind::FileList => "folder-documents-symbolic",
+        ContentKind::Color => "color-select-symbolic",
+        ContentKind::Json => "text-x-script-symbolic",
+        ContentKind::Code => "utilities-terminal-symbolic",
+        ContentKind::Phone => "call-start-symbolic",
+        ContentKind::Image => "image-x-generic-symbolic",
+    }
+}
+
+/// Overflow button with the selections to copy to, the context actions registered for the
+/// entry's kind and, for text entries, the "Copy As…" transformations.
+fn view_entry_menu<'a>(app: &AppModel, id: &cosmic::widget::Id, item: &ClipboardEntry) -> Option<Element<'a, Message>> {
+    let actions = app.actions.actions_for(item.kind);
+    let is_text = matches!(item.content, clipboard::ClipboardContent::Text(_));
+    if item.sensitive {
+        return None;
+    }
+
+    let menu_toggle = widget::button::icon(widget::icon::from_name("view-more-symbolic").handle())
+        .tooltip(fl!("more-actions"))
+        .on_press(Message::ToggleEntryMenu(id.clone()))
+        .extra_small()
+        .width(Length::Shrink);
+
+    let popover = widget::popover(menu_toggle).on_close(Message::CloseEntryMenu);
+    if app.open_menu.as_ref() != Some(id) {
+        return Some(popover.into());
+    }
+
+    let menu_item = |icon: &'static str, label: String| {
+        widget::row()
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .push(widget::icon::from_name(icon).size(16).icon())
+            .push(widget::text::body(label).width(Length::Fill))
+    };
+
+    let mut menu = widget::column()
+        .padding(4)
+        .push(
+            menu_button(menu_item("edit-paste-symbolic", fl!("copy-to-primary")))
+                .on_press(Message::CopyToSelection(id.clone(), WriteTarget::Primary)),
+        )
+        .push(
+            menu_button(menu_item("edit-paste-symbolic", fl!("copy-to-both")))
+                .on_press(Message::CopyToSelection(id.clone(), WriteTarget::Both)),
+        );
+
+    if !actions.is_empty() {
+        menu = menu.push(widget::divider::horizontal::light());
+    }
+    for action in actions {
+        menu = menu.push(
+            menu_button(menu_item(action.icon, action.label()))
+                .on_press(Message::RunEntryAction(id.clone(), *action)),
+        );
+    }
+
+    if is_text {
+        menu = menu.push(widget::divider::horizontal::light());
+
+        let expand_icon = if app.copy_as_expanded { "go-down-symbolic" } else { "go-next-symbolic" };
+        menu = menu.push(
+            menu_button(menu_item("edit-copy-symbolic", fl!("copy-as")).push(widget::icon::from_name(expand_icon).size(16).icon()))
+                .on_press(Message::ToggleCopyAs),
+        );
+
+        if app.copy_as_expanded {
+            let mut transforms = widget::column().padding([0, 0, 0, 24]);
+            for transform in Transform::ALL {
+                transforms = transforms.push(
+                    menu_button(widget::text::body(transform.label()))
+                        .on_press(Message::CopyTransformed(id.clone(), transform)),
+                );
             }
-            //     clipboard::ClipboardEntry::Image {
-            //         mime,
-            //         bytes,
-            //         thumbnail_png,
-            //         ..
-            //     } => {
-            //         let thumb = thumbnail_png
-            //             .as_ref()
-            //             .map(|png| widget::image(ImageHandle::from_bytes(png.clone())));
-            //
-            //         let meta = widget::text::body(format!(
-            //             "{} ({} KB)",
-            //             mime,
-            //             (bytes.len().saturating_add(1023)) / 1024
-            //         ));
-            //
-            //         let mut col = widget::column::Column::new()
-            //             .spacing(4)
-            //             .align_x(Alignment::Center);
-            //         if let Some(thumb) = thumb {
-            //             col = col.push(thumb);
-            //         }
-            //         col.push(meta).into()
-            //     }
+            transforms = transforms.push(
+                widget::settings::item(
+                    fl!("save-as-new-entry"),
+                    widget::toggler(app.save_transformed).on_toggle(Message::SetSaveTransformed),
+                ),
+            );
+            menu = menu.push(widget::scrollable(transforms).height(Length::Fixed(240.0)));
+        }
+
+        if !app.config.custom_actions.is_empty() {
+            menu = menu.push(widget::divider::horizontal::light());
+        }
+        for (index, action) in app.config.custom_actions.iter().enumerate() {
+            menu = menu.push(
+                menu_button(menu_item("system-run-symbolic", action.name.clone()))
+                    .on_press(Message::RunCustomAction(id.clone(), index)),
+            );
+        }
+    }
+
+    if !app.config.collections.is_empty() {
+        menu = menu
+            .push(widget::divider::horizontal::light())
+            .push(widget::container(widget::text::caption(fl!("move-to-collection"))).padding([4, 8]));
+    }
+    for name in &app.config.collections {
+        let mut button = menu_button(menu_item("folder-symbolic", name.clone()));
+        if item.collection.as_ref() != Some(name) {
+            button = button.on_press(Message::SetEntryCollection(id.clone(), Some(name.clone())));
         }
+        menu = menu.push(button);
     }
+    if !app.config.collections.is_empty() && item.collection.is_some() {
+        menu = menu.push(
+            menu_button(menu_item("edit-clear-symbolic", fl!("no-collection")))
+                .on_press(Message::SetEntryCollection(id.clone(), None)),
+        );
+    }
+
+    let menu = widget::container(menu)
+        .class(cosmic::theme::Container::Dropdown)
+        .width(Length::Fixed(260.0));
+
+    Some(popover.popup(menu).into())
 }
 
-pub fn create_clipboard_row<'a>(app: &AppModel, id: &cosmic::widget::Id, item: &'a ClipboardEntry) -> Element<'a, Message> {
-    let label = view_clipboard_entry(&app, &item);
+pub fn create_clipboard_row<'a>(app: &AppModel, id: &cosmic::widget::Id, item: &'a ClipboardEntry, found: &SearchMatch) -> Element<'a, Message> {
+    let label = view_clipboard_entry(&app, &item, &found.title_ranges);
+    let kind_icon = widget::icon::from_name(kind_icon_name(item.kind)).size(16).icon();
 
     let (pin_button_label, pin_button_icon) = if item.pinned {
         (fl!("unpin-item"), widget::icon::from_name("pin-symbolic").handle())
@@ -110,6 +302,49 @@ pub fn create_clipboard_row<'a>(app: &AppModel, id: &cosmic::widget::Id, item: &
         .extra_small()
         .width(Length::Shrink);
 
+    let is_image = matches!(item.content, clipboard::ClipboardContent::Image { .. });
+    let preview_button = (is_image && !item.sensitive).then(|| {
+        widget::button::icon(widget::icon::from_name("zoom-in-symbolic").handle())
+            .tooltip(fl!("preview-image"))
+            .on_press(Message::TogglePreview(id.clone()))
+            .extra_small()
+            .width(Length::Shrink)
+    });
+
+    let (sensitive_button_label, sensitive_button_icon) = if item.sensitive {
+        (fl!("reveal-item"), widget::icon::from_name("view-reveal-symbolic").handle())
+    } else {
+        (fl!("conceal-item"), widget::icon::from_name("view-conceal-symbolic").handle())
+    };
+
+    let sensitive_button = widget::button::icon(sensitive_button_icon)
+        .tooltip(sensitive_button_label)
+        .on_press(Message::ToggleSensitive(id.clone()))
+        .extra_small()
+        .width(Length::Shrink);
+
+    let tags_editing = app.tagging_entry.as_ref() == Some(id);
+    let tag_button = widget::button::icon(widget::icon::from_name("bookmark-new-symbolic").handle())
+        .tooltip(fl!("edit-tags"))
+        .on_press(Message::ToggleTagEditor(id.clone()))
+        .extra_small()
+        .width(Length::Shrink);
+
+    // Chips search for their tag, or remove it while the tag editor is open.
+    let tag_chips = (!item.tags.is_empty()).then(|| {
+        let mut chips = widget::row().spacing(4).align_y(Alignment::Center);
+        for tag in &item.tags {
+            let chip = widget::button::text(format!("#{tag}"));
+            chips = chips.push(if tags_editing {
+                chip.trailing_icon(widget::icon::from_name("window-close-symbolic").handle())
+                    .on_press(Message::RemoveTag(id.clone(), tag.clone()))
+            } else {
+                chip.on_press(Message::SearchInputChanged(format!("#{tag}")))
+            });
+        }
+        chips
+    });
+
     let remove_button =
         widget::button::icon(widget::icon::from_name("list-remove-symbolic").handle())
             .tooltip(fl!("remove"))
@@ -117,18 +352,364 @@ pub fn create_clipboard_row<'a>(app: &AppModel, id: &cosmic::widget::Id, item: &
             .extra_small()
             .width(Length::Shrink);
 
-    widget::row::Row::new()
+    let row = widget::row::Row::new()
         .spacing(8)
         .padding([4, 0])
         .align_y(Alignment::Center)
+        .push(kind_icon)
         .push(label)
+        .push_maybe(tag_chips)
+        .push_maybe(preview_button)
+        .push(tag_button)
         .push(pin_button)
+        .push(sensitive_button)
         .push(if app.editing_entry == Some(item.widget_id.clone()) { save_button } else { edit_button })
         .push(remove_button)
+        .push_maybe(view_entry_menu(app, id, item));
+
+    let row: Element<'a, Message> = if tags_editing {
+        widget::column().push(row).push(view_tag_editor(app, id)).into()
+    } else {
+        row.into()
+    };
+
+    if app.focused_entry.as_ref() == Some(id) {
+        widget::container(row)
+            .class(cosmic::theme::Container::Primary)
+            .into()
+    } else {
+        row.into()
+    }
+}
+
+/// Input for new tags with the existing tags that complete the word being typed.
+fn view_tag_editor<'a>(app: &AppModel, id: &cosmic::widget::Id) -> Element<'a, Message> {
+    let input = widget::inline_input(fl!("add-tags"), app.tag_input.clone())
+        .id(TAG_INPUT_ID.clone())
+        .on_input(Message::TagInputChanged)
+        .on_submit(Message::TagInputSubmitted)
+        .width(Length::Fixed(200.0));
+
+    let mut editor = widget::row()
+        .spacing(4)
+        .padding([0, 0, 4, 24])
+        .align_y(Alignment::Center)
+        .push(input);
+    for tag in app.tag_suggestions() {
+        editor = editor.push(
+            widget::button::text(format!("#{tag}")).on_press(Message::AddTag(id.clone(), tag.to_string())),
+        );
+    }
+    editor.into()
+}
+
+fn view_pinned_row<'a>(app: &AppModel, item: &'a ClipboardEntry, found: &SearchMatch) -> Element<'a, Message> {
+    let row = create_clipboard_row(app, &item.widget_id, item, found);
+    if app.can_reorder_pinned() {
+        view_draggable_pinned_row(app, item, row)
+    } else {
+        row
+    }
+}
+
+/// Collapsible heading of a collection, with its name turned into an input while renaming.
+fn view_collection_header<'a>(app: &'a AppModel, name: &'a str, count: usize) -> Element<'a, Message> {
+    let expand_icon = if app.is_collection_collapsed(name) { "go-next-symbolic" } else { "go-down-symbolic" };
+    let toggle = widget::button::icon(widget::icon::from_name(expand_icon).handle())
+        .tooltip(fl!("toggle-collection"))
+        .on_press(Message::ToggleCollectionCollapsed(name.to_string()))
+        .extra_small()
+        .width(Length::Shrink);
+
+    let title: Element<'a, Message> = if app.editing_collection.as_deref() == Some(name) {
+        widget::inline_input("", &app.collection_name_input)
+            .id(COLLECTION_INPUT_ID.clone())
+            .on_input(Message::CollectionNameChanged)
+            .on_submit(Message::CollectionNameSubmitted)
+            .select_on_focus(true)
+            .width(Length::Fill)
+            .into()
+    } else {
+        widget::text::heading(format!("{name} ({count})"))
+            .width(Length::Fill)
+            .into()
+    };
+
+    let rename_button = widget::button::icon(widget::icon::from_name("edit-symbolic").handle())
+        .tooltip(fl!("rename-collection"))
+        .on_press(Message::EditCollectionName(name.to_string()))
+        .extra_small()
+        .width(Length::Shrink);
+
+    let delete_button = widget::button::icon(widget::icon::from_name("list-remove-symbolic").handle())
+        .tooltip(fl!("delete-collection"))
+        .on_press(Message::DeleteCollection(name.to_string()))
+        .extra_small()
+        .width(Length::Shrink);
+
+    widget::row()
+        .spacing(8)
+        .padding([8, 0, 4, 0])
+        .align_y(Alignment::Center)
+        .push(toggle)
+        .push(title)
+        .push(rename_button)
+        .push(delete_button)
+        .into()
+}
+
+/// Pinned row with a drag handle; releasing a dragged entry over the row moves it there.
+fn view_draggable_pinned_row<'a>(
+    app: &AppModel,
+    item: &'a ClipboardEntry,
+    row: Element<'a, Message>,
+) -> Element<'a, Message> {
+    let id = &item.widget_id;
+    let drag_handle = mouse_area(widget::icon::from_name("list-drag-handle-symbolic").size(16))
+        .interaction(mouse::Interaction::Grab)
+        .on_press(Message::PinnedDragStarted(id.clone()));
+
+    let content = widget::row()
+        .spacing(4)
+        .align_y(Alignment::Center)
+        .push(drag_handle)
+        .push(row);
+
+    let is_drop_target = app
+        .pinned_drag
+        .as_ref()
+        .is_some_and(|drag| drag.id != *id && drag.over.as_ref() == Some(id));
+    let content: Element<'a, Message> = if is_drop_target {
+        widget::container(content)
+            .class(cosmic::theme::Container::Card)
+            .into()
+    } else {
+        content.into()
+    };
+
+    mouse_area(content)
+        .on_enter(Message::PinnedDragEntered(id.clone()))
+        .on_release(Message::PinnedDropped(id.clone()))
+        .into()
+}
+
+pub fn view_settings(app: &AppModel) -> Element<'_, Message> {
+    let config = &app.config;
+
+    let back_button = widget::button::icon(widget::icon::from_name("go-previous-symbolic").handle())
+        .tooltip(fl!("back"))
+        .on_press(Message::ToggleSettings);
+
+    let header = widget::row()
+        .spacing(8)
+        .align_y(Alignment::Center)
+        .push(back_button)
+        .push(widget::text::heading(fl!("settings")));
+
+    let max_image_mb = config.max_image_bytes / (1024 * 1024);
+
+    let history_section = widget::settings::section()
+        .title(fl!("settings-history"))
+        .add(widget::settings::item(
+            fl!("settings-max-history"),
+            widget::spin_button(
+                config.max_history.to_string(),
+                config.max_history,
+                1,
+                MIN_HISTORY,
+                500,
+                Message::SetMaxHistory,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-title-max-chars"),
+            widget::spin_button(
+                config.title_max_chars.to_string(),
+                config.title_max_chars,
+                1,
+                5,
+                200,
+                Message::SetTitleMaxChars,
+            ),
+        ));
+
+    let capture_section = widget::settings::section()
+        .title(fl!("settings-capture"))
+        .add(widget::settings::item(
+            fl!("settings-max-image-size"),
+            widget::spin_button(
+                format!("{max_image_mb} MB"),
+                max_image_mb,
+                1,
+                1,
+                256,
+                Message::SetMaxImageMegabytes,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-show-thumbnails"),
+            widget::toggler(config.show_thumbnails).on_toggle(Message::SetShowThumbnails),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-thumbnail-size"),
+            widget::spin_button(
+                format!("{} px", config.thumbnail_size_px),
+                config.thumbnail_size_px,
+                8,
+                16,
+                256,
+                Message::SetThumbnailSize,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-poll-interval"),
+            widget::spin_button(
+                format!("{} ms", config.poll_interval_ms),
+                config.poll_interval_ms,
+                100,
+                100,
+                10_000,
+                Message::SetPollInterval,
+            ),
+        ))
+        .add(
+            widget::settings::item::builder(fl!("settings-keep-clipboard-alive"))
+                .description(fl!("settings-keep-clipboard-alive-description"))
+                .toggler(config.keep_clipboard_alive, Message::SetKeepClipboardAlive),
+        )
+        .add(widget::settings::item(
+            fl!("settings-track-primary"),
+            widget::toggler(config.track_primary).on_toggle(Message::SetTrackPrimary),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-max-primary-history"),
+            widget::spin_button(
+                config.max_primary_history.to_string(),
+                config.max_primary_history,
+                1,
+                1,
+                100,
+                Message::SetMaxPrimaryHistory,
+            ),
+        ));
+
+    // Zero turns a retention limit off.
+    let limit_label = |value: usize, off: String, unit: &str| {
+        if value == 0 { off } else { format!("{value}{unit}") }
+    };
+    let max_history_mb = config.max_history_bytes / (1024 * 1024);
+
+    let retention_section = widget::settings::section()
+        .title(fl!("settings-retention"))
+        .add(widget::settings::item(
+            fl!("settings-max-text-entries"),
+            widget::spin_button(
+                limit_label(config.max_text_entries, fl!("settings-unlimited"), ""),
+                config.max_text_entries,
+                1,
+                0,
+                500,
+                Message::SetMaxTextEntries,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-max-image-entries"),
+            widget::spin_button(
+                limit_label(config.max_image_entries, fl!("settings-unlimited"), ""),
+                config.max_image_entries,
+                1,
+                0,
+                500,
+                Message::SetMaxImageEntries,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-max-history-size"),
+            widget::spin_button(
+                limit_label(max_history_mb, fl!("settings-unlimited"), " MB"),
+                max_history_mb,
+                10,
+                0,
+                4096,
+                Message::SetMaxHistoryMegabytes,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-max-age"),
+            widget::spin_button(
+                limit_label(config.max_age_hours as usize, fl!("settings-never"), " h"),
+                config.max_age_hours,
+                1,
+                0,
+                24 * 365,
+                Message::SetMaxAgeHours,
+            ),
+        ))
+        .add(widget::settings::item(
+            fl!("settings-sensitive-expiry"),
+            widget::spin_button(
+                limit_label(config.sensitive_expiry_secs as usize, fl!("settings-never"), " s"),
+                config.sensitive_expiry_secs,
+                10,
+                0,
+                24 * 60 * 60,
+                Message::SetSensitiveExpirySecs,
+            ),
+        ));
+
+    widget::column()
+        .spacing(12)
+        .push(header)
+        .push(history_section)
+        .push(capture_section)
+        .push(retention_section)
+        .into()
+}
+
+pub fn view_template_prompt<'a>(session: &'a PromptSession) -> Element<'a, Message> {
+    let label = session.current_prompt().unwrap_or_default();
+
+    let input = widget::text_input(label, &session.input)
+        .id(TEMPLATE_INPUT_ID.clone())
+        .on_input(Message::TemplateInputChanged)
+        .on_submit(Message::TemplateInputSubmitted)
+        .width(Length::Fill);
+
+    let cancel_button = widget::button::icon(widget::icon::from_name("window-close-symbolic").handle())
+        .tooltip(fl!("cancel"))
+        .on_press(Message::CancelTemplate);
+
+    widget::column()
+        .spacing(8)
+        .push(widget::text::heading(label))
+        .push(
+            widget::row()
+                .spacing(8)
+                .align_y(Alignment::Center)
+                .push(input)
+                .push(cancel_button),
+        )
         .into()
 }
 
 pub fn view_window(app: &AppModel, _id: Id) -> Element<'_, Message> {
+    if app.show_settings {
+        let settings = widget::container(widget::scrollable(view_settings(app)).height(Length::Fixed(500.0)))
+            .padding(Padding::from(8));
+        return app.core.applet.popup_container(settings)
+            .min_width(700f32)
+            .max_width(800f32)
+            .into();
+    }
+
+    if let Some(session) = &app.prompt_session {
+        let prompt = widget::container(view_template_prompt(session)).padding(Padding::from(8));
+        return app.core.applet.popup_container(prompt)
+            .min_width(700f32)
+            .max_width(800f32)
+            .into();
+    }
+
     let search_box = text_input::search_input(fl!("search-entries"), &app.search_filter)
         .always_active()
         .on_input(Message::SearchInputChanged)
@@ -136,8 +717,12 @@ pub fn view_window(app: &AppModel, _id: Id) -> Element<'_, Message> {
         .on_clear(Message::SearchInputChanged("".to_string()));
 
     let settings = widget::button::icon(widget::icon::from_name("emblem-system-symbolic").handle())
-        .tooltip("Modify Settings")
-        .on_press(Message::ClearHistory); // TODO: Add message for settings gear
+        .tooltip(fl!("settings"))
+        .on_press(Message::ToggleSettings);
+
+    let new_collection = widget::button::icon(widget::icon::from_name("folder-new-symbolic").handle())
+        .tooltip(fl!("new-collection"))
+        .on_press(Message::CreateCollection(fl!("collection")));
 
     let clear_all = widget::button::icon(widget::icon::from_name("edit-delete-symbolic").handle())
         .tooltip("Clear Clipboard History")
@@ -146,23 +731,71 @@ pub fn view_window(app: &AppModel, _id: Id) -> Element<'_, Message> {
     let top_row = widget::row().padding([2,0]).spacing(8)
         .push(settings)
         .push(search_box.width(Length::Fill))
+        .push(new_collection)
         .push(clear_all); // row![search_box, settings_gear].padding([8, 0]).spacing(8); // widget::row().padding([8, 0]).spacing(8);
 
+    let search_error = app
+        .search_error
+        .as_ref()
+        .map(|err| widget::container(widget::warning(err.to_string())).padding([4, 0]));
+
+    let action_error = app
+        .action_error
+        .as_ref()
+        .map(|err| widget::container(widget::warning(err.clone())).padding([4, 0]));
+
     let mut pinned_rows: Column<Message> = widget::column().into();
-    for (id, item) in &app.pinned_clipboard_entries {
-        if app.search_filter.is_empty() || item.title.contains(app.search_filter.as_str()) {
-            pinned_rows = pinned_rows.push(create_clipboard_row(&app, &id, &item));
+    for (item, found) in app.visible_pinned_entries_in(None) {
+        pinned_rows = pinned_rows.push(view_pinned_row(app, item, found));
+    }
+    for name in &app.config.collections {
+        let entries = app.visible_pinned_entries_in(Some(name));
+        // While searching, collections without matches are left out entirely.
+        if entries.is_empty() && !app.search_filter.trim().is_empty() {
+            continue;
+        }
+        pinned_rows = pinned_rows.push(view_collection_header(app, name, entries.len()));
+        if app.is_collection_collapsed(name) {
+            continue;
+        }
+        for (item, found) in entries {
+            pinned_rows = pinned_rows.push(view_pinned_row(app, item, found));
         }
     }
+    // Releasing or leaving the pinned section anywhere but on a row ends the drag.
+    let pinned_rows = mouse_area(pinned_rows)
+        .on_release(Message::PinnedDragCancelled)
+        .on_exit(Message::PinnedDragCancelled);
 
-    let mut unpinned_rows: Column<Message> = widget::column().into();
-    for (id, item) in &app.clipboard_entries {
-        if app.search_filter.is_empty() || item.title.contains(app.search_filter.as_str()) {
-            unpinned_rows = unpinned_rows.push(create_clipboard_row(&app, &id, &item));
+    let mut snippet_rows: Column<Message> = widget::column().into();
+    for (index, snippet) in app.snippets.iter().enumerate() {
+        let query = app.search_query.text();
+        if query.trim().is_empty() || search::fuzzy_match(query, &snippet.name).is_some() {
+            snippet_rows = snippet_rows.push(
+                menu_button(widget::text::body(&snippet.name))
+                    .on_press(Message::CopySnippet(index)),
+            );
         }
     }
+    let snippets_header = (!app.snippets.is_empty()).then(|| {
+        widget::container(widget::text::heading(fl!("snippets"))).padding([8, 0, 4, 0])
+    });
 
-    let total_clipboard_items = app.clipboard_entries.len() + app.pinned_clipboard_entries.len();
+    let mut unpinned_rows: Column<Message> = widget::column().into();
+    for (item, found) in app.visible_unpinned_entries() {
+        unpinned_rows = unpinned_rows.push(create_clipboard_row(&app, &item.widget_id, &item, found));
+    }
+
+    let mut primary_rows: Column<Message> = widget::column().into();
+    let visible_primary = app.visible_primary_entries();
+    let primary_header = (!visible_primary.is_empty()).then(|| {
+        widget::container(widget::text::heading(fl!("primary-selection"))).padding([8, 0, 4, 0])
+    });
+    for (item, found) in visible_primary {
+        primary_rows = primary_rows.push(create_clipboard_row(&app, &item.widget_id, &item, found));
+    }
+
+    let total_clipboard_items = app.entries.len();
     let empty_label = (total_clipboard_items == 0).then_some(
         widget::container(
             widget::text::text(fl!("empty")))
@@ -174,14 +807,24 @@ pub fn view_window(app: &AppModel, _id: Id) -> Element<'_, Message> {
 
     let mut display = widget::column().padding(Padding::from(8)).spacing(0)
         .push(top_row)
+        .push_maybe(search_error)
+        .push_maybe(action_error)
         .push_maybe(empty_label)
         .push(pinned_rows)
+        .push_maybe(snippets_header)
+        .push(snippet_rows)
         .push(unpinned_rows)
+        .push_maybe(primary_header)
+        .push(primary_rows)
         .apply(widget::scrollable)
         // .width(Length::Fixed(800f32))
-        .height(if total_clipboard_items > 5 { Length::Fixed(400.0) } else { Length::Shrink });
+        .height(if total_clipboard_items + app.snippets.len() > 5 { Length::Fixed(400.0) } else { Length::Shrink });
+
+    let content = widget::column()
+        .push(display)
+        .push_maybe(view_image_preview(app));
 
-    app.core.applet.popup_container(display)
+    app.core.applet.popup_container(content)
         .min_width(700f32)
         .max_width(800f32)
         .into()
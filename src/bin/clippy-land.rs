@@ -0,0 +1,339 @@
+//! `clippy-land`: scripts the clipboard history of the running applet over D-Bus, or the saved
+//! history directly when the applet is not running.
+
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+use clap::{Parser, Subcommand};
+use cosmic_applet_clippy_land::config::Config;
+use cosmic_applet_clippy_land::i18n;
+use cosmic_applet_clippy_land::services::backend::WaylandClipboard;
+use cosmic_applet_clippy_land::services::clipboard::{self, ClipboardContent, ClipboardEntry, ClipboardSource};
+use cosmic_applet_clippy_land::services::dbus::{self, EntryInfo, HistoryProxy};
+use cosmic_applet_clippy_land::services::query::Query;
+use cosmic_applet_clippy_land::services::retention::Retention;
+use cosmic_applet_clippy_land::services::search;
+use cosmic_applet_clippy_land::services::storage::HistoryStore;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(name = "clippy-land", version, about = "Script the Clippy Land clipboard history")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Entries are addressed by their id or by their 1-based position in `list`.
+#[derive(Subcommand)]
+enum Command {
+    /// List all entries, pinned first
+    List {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the content of an entry
+    Get { entry: String },
+    /// Put an entry on the clipboard
+    Copy { entry: String },
+    /// Add the text read from stdin as a new entry
+    Add {
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Pin an entry
+    Pin { entry: String },
+    /// Unpin an entry
+    Unpin { entry: String },
+    /// Remove an entry
+    Rm { entry: String },
+    /// Remove all unpinned entries
+    Clear,
+    /// List entries matching a query in the search box syntax, best match first
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Where the history lives: the running applet, or the store it saves to.
+enum Backend {
+    Applet(HistoryProxy<'static>),
+    Store { store: HistoryStore, config: Config },
+}
+
+impl Backend {
+    async fn connect() -> Result<Self> {
+        if let Some(proxy) = applet_proxy().await {
+            return Ok(Backend::Applet(proxy));
+        }
+
+        let store = HistoryStore::open_default().ok_or("no data directory for the saved history")?;
+        let (_, config) = Config::load();
+        Ok(Backend::Store { store, config })
+    }
+
+    async fn list(&self) -> Result<Vec<EntryInfo>> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.list().await?),
+            Backend::Store { store, config } => Ok(store
+                .load(config.thumbnail_size_px)
+                .iter()
+                .map(EntryInfo::from_entry)
+                .collect()),
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<(EntryInfo, Vec<u8>)> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.get(id).await?),
+            Backend::Store { store, config } => {
+                let entries = store.load(config.thumbnail_size_px);
+                let entry = find(&entries, id)?;
+                let bytes = match &entry.content {
+                    ClipboardContent::Text(text) => text.as_bytes().to_vec(),
+                    ClipboardContent::Image { bytes, .. } => bytes.clone(),
+                };
+                Ok((EntryInfo::from_entry(entry), bytes))
+            }
+        }
+    }
+
+    async fn copy(&self, id: &str) -> Result<()> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.copy(id).await?),
+            Backend::Store { store, config } => {
+                let entries = store.load(config.thumbnail_size_px);
+                if !clipboard::write_clipboard_entry(&WaylandClipboard, find(&entries, id)?) {
+                    return Err("could not write to the clipboard".into());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn add(&self, title: &str, text: String) -> Result<String> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.add(title, &text).await?),
+            Backend::Store { store, config } => {
+                let mut entries = store.load(config.thumbnail_size_px);
+                let title = match title.trim() {
+                    "" => clipboard::summarize_one_line(&text, config.title_max_chars),
+                    title => title.to_string(),
+                };
+                if text.trim().is_empty() {
+                    return Err("the text is empty".into());
+                }
+                let content = ClipboardContent::Text(text);
+                let pinned = entries.iter().filter(|entry| entry.pinned).count();
+
+                // Same dedupe as the applet: a repeated copy moves the existing entry of the
+                // clipboard track to the top; a new one keeps the tags of its other copies.
+                let entry = match entries.iter().position(|entry| {
+                    !entry.pinned && entry.source == ClipboardSource::Regular && entry.content == content
+                }) {
+                    Some(index) => {
+                        let mut entry = entries.remove(index);
+                        entry.last_copied_at = SystemTime::now();
+                        entry
+                    }
+                    None => {
+                        let mut entry = ClipboardEntry::new(title, content);
+                        entry.source = ClipboardSource::Regular;
+                        for other in entries.iter().filter(|other| other.content == entry.content) {
+                            for tag in &other.tags {
+                                entry.add_tag(tag);
+                            }
+                        }
+                        entry
+                    }
+                };
+
+                let id = entry.id;
+                entries.insert(pinned, entry);
+                let retention = Retention::from_config(config);
+                let mut keep = retention.keeper(SystemTime::now());
+                entries.retain(|entry| entry.pinned || keep(entry));
+                store.save(&entries)?;
+
+                // The new entry itself can be over the size budget.
+                if !entries.iter().any(|entry| entry.id == id) {
+                    return Err("the entry is over the history size limit".into());
+                }
+                Ok(id.to_string())
+            }
+        }
+    }
+
+    async fn set_pinned(&self, id: &str, pinned: bool) -> Result<()> {
+        match self {
+            Backend::Applet(proxy) if pinned => Ok(proxy.pin(id).await?),
+            Backend::Applet(proxy) => Ok(proxy.unpin(id).await?),
+            Backend::Store { store, config } => {
+                let mut entries = store.load(config.thumbnail_size_px);
+                let index = position(&entries, id)?;
+                if entries[index].pinned != pinned {
+                    // Like the applet: newly pinned entries go last among the pinned ones,
+                    // unpinned ones to the end of the history.
+                    let mut entry = entries.remove(index);
+                    entry.pinned = pinned;
+                    if !pinned {
+                        // Collections only group pinned entries.
+                        entry.collection = None;
+                    }
+                    let target = if pinned {
+                        entries.iter().filter(|entry| entry.pinned).count()
+                    } else {
+                        entries.len()
+                    };
+                    entries.insert(target, entry);
+                    store.save(&entries)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn remove(&self, id: &str) -> Result<()> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.remove(id).await?),
+            Backend::Store { store, config } => {
+                let mut entries = store.load(config.thumbnail_size_px);
+                entries.remove(position(&entries, id)?);
+                Ok(store.save(&entries)?)
+            }
+        }
+    }
+
+    async fn clear(&self) -> Result<()> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.clear().await?),
+            Backend::Store { store, config } => {
+                let mut entries = store.load(config.thumbnail_size_px);
+                entries.retain(|entry| entry.pinned);
+                Ok(store.save(&entries)?)
+            }
+        }
+    }
+
+    async fn search(&self, query: &str) -> Result<Vec<EntryInfo>> {
+        match self {
+            Backend::Applet(proxy) => Ok(proxy.search(query).await?),
+            Backend::Store { store, config } => {
+                let query = Query::parse(query).map_err(|err| err.to_string())?;
+                let entries = store.load(config.thumbnail_size_px);
+                let pinned = search::search(&query, entries.iter().filter(|entry| entry.pinned));
+                let unpinned = search::search(&query, entries.iter().filter(|entry| !entry.pinned));
+                Ok(pinned
+                    .into_iter()
+                    .chain(unpinned)
+                    .map(|(entry, _)| EntryInfo::from_entry(entry))
+                    .collect())
+            }
+        }
+    }
+
+    /// Turns a 1-based position from `list` into an id; anything else is taken as an id.
+    async fn resolve(&self, entry: &str) -> Result<String> {
+        let Ok(index) = entry.parse::<usize>() else {
+            return Ok(entry.to_string());
+        };
+
+        self.list()
+            .await?
+            .into_iter()
+            .nth(index.wrapping_sub(1))
+            .map(|info| info.id)
+            .ok_or_else(|| format!("no entry at position {index}").into())
+    }
+}
+
+/// A proxy for the running applet, or `None` when it isn't on the session bus.
+async fn applet_proxy() -> Option<HistoryProxy<'static>> {
+    let connection = zbus::Connection::session().await.ok()?;
+    let bus = zbus::fdo::DBusProxy::new(&connection).await.ok()?;
+    let name = dbus::BUS_NAME.try_into().ok()?;
+    if !bus.name_has_owner(name).await.ok()? {
+        return None;
+    }
+    HistoryProxy::new(&connection).await.ok()
+}
+
+fn position(entries: &[ClipboardEntry], id: &str) -> Result<usize> {
+    entries
+        .iter()
+        .position(|entry| entry.id.to_string() == id)
+        .ok_or_else(|| format!("no entry with id {id}").into())
+}
+
+fn find<'a>(entries: &'a [ClipboardEntry], id: &str) -> Result<&'a ClipboardEntry> {
+    Ok(&entries[position(entries, id)?])
+}
+
+/// Tab separated: position, id, kind, `pinned` or `-`, title.
+fn print_entries(entries: &[EntryInfo], json: bool) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, entries)?;
+        writeln!(stdout)?;
+        return Ok(());
+    }
+
+    for (index, entry) in entries.iter().enumerate() {
+        let pinned = if entry.pinned { "pinned" } else { "-" };
+        writeln!(
+            stdout,
+            "{}\t{}\t{}\t{}\t{}",
+            index + 1,
+            entry.id,
+            entry.kind,
+            pinned,
+            entry.title
+        )?;
+    }
+    Ok(())
+}
+
+async fn run(command: Command) -> Result<()> {
+    let backend = Backend::connect().await?;
+
+    match command {
+        Command::List { json } => print_entries(&backend.list().await?, json)?,
+        Command::Get { entry } => {
+            let (_, bytes) = backend.get(&backend.resolve(&entry).await?).await?;
+            io::stdout().lock().write_all(&bytes)?;
+        }
+        Command::Copy { entry } => backend.copy(&backend.resolve(&entry).await?).await?,
+        Command::Add { title } => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            if text.is_empty() {
+                return Err("nothing to add, stdin was empty".into());
+            }
+            println!("{}", backend.add(title.as_deref().unwrap_or_default(), text).await?);
+        }
+        Command::Pin { entry } => backend.set_pinned(&backend.resolve(&entry).await?, true).await?,
+        Command::Unpin { entry } => backend.set_pinned(&backend.resolve(&entry).await?, false).await?,
+        Command::Rm { entry } => backend.remove(&backend.resolve(&entry).await?).await?,
+        Command::Clear => backend.clear().await?,
+        Command::Search { query, json } => print_entries(&backend.search(&query.join(" ")).await?, json)?,
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    i18n::init(&i18n_embed::DesktopLanguageRequester::requested_languages());
+
+    match run(Cli::parse().command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("clippy-land: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use cosmic::cosmic_config::{self, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry};
+
+use crate::services::commands::CustomAction;
+
+/// Unique identifier in RDNN (reverse domain name notation) format, shared by the applet and CLI.
+pub const APP_ID: &str = "com.keewee.CosmicAppletClippyLand";
+
+/// Smallest `max_history`; lower values from a hand-edited config are raised to it.
+pub const MIN_HISTORY: usize = 1;
+
+/// User settings, stored through cosmic-config under the applet's app ID.
+#[derive(Debug, Clone, CosmicConfigEntry, PartialEq, Eq, Hash)]
+#[version = 1]
+pub struct Config {
+    /// Number of unpinned entries kept in the history, at least [`MIN_HISTORY`].
+    pub max_history: usize,
+    /// Images larger than this are not recorded.
+    pub max_image_bytes: usize,
+    /// Show image thumbnails in the history rows.
+    pub show_thumbnails: bool,
+    /// Edge length of generated image thumbnails.
+    pub thumbnail_size_px: u32,
+    /// Clipboard poll interval, only used when the compositor lacks data-control support.
+    pub poll_interval_ms: u64,
+    /// Length of the title generated for text entries.
+    pub title_max_chars: usize,
+    /// Also record the primary selection (text selected with the mouse).
+    pub track_primary: bool,
+    /// Number of unpinned primary selection entries kept, on top of `max_history`.
+    pub max_primary_history: usize,
+    /// Unpinned text entries kept across both selections, 0 for no separate limit.
+    pub max_text_entries: usize,
+    /// Unpinned image entries kept across both selections, 0 for no separate limit.
+    pub max_image_entries: usize,
+    /// Total size of the unpinned entries, extra formats included, 0 for no budget.
+    pub max_history_bytes: usize,
+    /// Unpinned entries not copied for this long are dropped, 0 keeps them.
+    pub max_age_hours: u64,
+    /// Unpinned hidden entries are dropped this long after they were copied, 0 keeps them.
+    pub sensitive_expiry_secs: u64,
+    /// Re-offer each new clipboard selection from the applet so it outlives the source application.
+    /// Only the formats an entry records survive; see `EXTRA_MIMES` in the clipboard service.
+    pub keep_clipboard_alive: bool,
+    /// User-defined commands offered in the menu of text entries.
+    pub custom_actions: Vec<CustomAction>,
+    /// Custom actions still running after this long are killed.
+    pub custom_action_timeout_secs: u64,
+    /// Names of the collections pinned entries can be grouped in, in display order.
+    pub collections: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_history: 30,
+            max_image_bytes: 8 * 1024 * 1024,
+            show_thumbnails: true,
+            thumbnail_size_px: 40,
+            poll_interval_ms: 500,
+            title_max_chars: 25,
+            track_primary: false,
+            max_primary_history: 10,
+            max_text_entries: 0,
+            max_image_entries: 0,
+            max_history_bytes: 0,
+            max_age_hours: 0,
+            sensitive_expiry_secs: 0,
+            keep_clipboard_alive: false,
+            custom_actions: Vec::new(),
+            custom_action_timeout_secs: 10,
+            collections: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the stored settings with their handler; errors fall back to the defaults.
+    pub fn load() -> (Option<cosmic_config::Config>, Config) {
+        let handler = cosmic_config::Config::new(APP_ID, Config::VERSION).ok();
+        let config = handler
+            .as_ref()
+            .map(|handler| match Config::get_entry(handler) {
+                Ok(config) => config,
+                Err((errors, config)) => {
+                    for err in errors {
+                        eprintln!("[clippy-land] error loading config: {err:?}");
+                    }
+                    config
+                }
+            })
+            .unwrap_or_default();
+
+        (handler, config)
+    }
+}
@@ -0,0 +1,5 @@
+//! Clipboard history services shared by the applet and the `clippy-land` command-line tool.
+
+pub mod config;
+pub mod i18n;
+pub mod services;
@@ -1,6 +1,6 @@
 mod app;
-mod i18n;
-mod services;
+
+use cosmic_applet_clippy_land::{config, fl, i18n, services};
 
 fn main() -> cosmic::iced::Result {
     let requested_languages = i18n_embed::DesktopLanguageRequester::requested_languages();
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::fl;
+use crate::services::clipboard::{ClipboardContent, ClipboardEntry, ContentKind};
+
+/// A one-click action offered in an entry's overflow menu.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryAction {
+    pub id: &'static str,
+    pub icon: &'static str,
+    label: fn() -> String,
+    run: fn(&ClipboardEntry) -> io::Result<()>,
+}
+
+impl PartialEq for EntryAction {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for EntryAction {}
+
+impl EntryAction {
+    pub const fn new(
+        id: &'static str,
+        icon: &'static str,
+        label: fn() -> String,
+        run: fn(&ClipboardEntry) -> io::Result<()>,
+    ) -> Self {
+        EntryAction { id, icon, label, run }
+    }
+
+    pub fn label(&self) -> String {
+        (self.label)()
+    }
+
+    pub fn run(&self, entry: &ClipboardEntry) -> io::Result<()> {
+        (self.run)(entry)
+    }
+}
+
+pub const OPEN_URL: EntryAction = EntryAction::new(
+    "open-url",
+    "web-browser-symbolic",
+    || fl!("action-open-url"),
+    |entry| open_with_default_handler(&normalize_url(entry_text(entry)?)),
+);
+
+pub const COMPOSE_MAIL: EntryAction = EntryAction::new(
+    "compose-mail",
+    "mail-send-symbolic",
+    || fl!("action-compose-mail"),
+    |entry| {
+        let address = entry_text(entry)?;
+        let address = address.strip_prefix("mailto:").unwrap_or(address);
+        open_with_default_handler(&format!("mailto:{address}"))
+    },
+);
+
+pub const CALL: EntryAction = EntryAction::new(
+    "call",
+    "call-start-symbolic",
+    || fl!("action-call"),
+    |entry| {
+        let number: String = entry_text(entry)?
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '+')
+            .collect();
+        open_with_default_handler(&format!("tel:{number}"))
+    },
+);
+
+pub const OPEN_FILE: EntryAction = EntryAction::new(
+    "open-file",
+    "document-open-symbolic",
+    || fl!("action-open-file"),
+    |entry| open_with_default_handler(&first_path(entry)?.to_string_lossy()),
+);
+
+pub const REVEAL_IN_FILE_MANAGER: EntryAction = EntryAction::new(
+    "reveal-in-file-manager",
+    "folder-open-symbolic",
+    || fl!("action-reveal"),
+    |entry| {
+        let path = first_path(entry)?;
+        let folder = if path.is_dir() {
+            path.as_path()
+        } else {
+            path.parent().unwrap_or(Path::new("/"))
+        };
+        open_with_default_handler(&folder.to_string_lossy())
+    },
+);
+
+/// Actions available per content kind. Register additional actions with [`ActionRegistry::register`].
+#[derive(Debug, Clone)]
+pub struct ActionRegistry {
+    actions: HashMap<ContentKind, Vec<EntryAction>>,
+}
+
+impl Default for ActionRegistry {
+    fn default() -> Self {
+        let mut registry = ActionRegistry {
+            actions: HashMap::new(),
+        };
+        registry.register(ContentKind::Url, OPEN_URL);
+        registry.register(ContentKind::Email, COMPOSE_MAIL);
+        registry.register(ContentKind::Phone, CALL);
+        for kind in [ContentKind::FilePath, ContentKind::FileList] {
+            registry.register(kind, OPEN_FILE);
+            registry.register(kind, REVEAL_IN_FILE_MANAGER);
+        }
+        registry
+    }
+}
+
+impl ActionRegistry {
+    pub fn register(&mut self, kind: ContentKind, action: EntryAction) {
+        let actions = self.actions.entry(kind).or_default();
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+
+    pub fn actions_for(&self, kind: ContentKind) -> &[EntryAction] {
+        self.actions.get(&kind).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+fn entry_text(entry: &ClipboardEntry) -> io::Result<&str> {
+    match &entry.content {
+        ClipboardContent::Text(text) => Ok(text.trim()),
+        ClipboardContent::Image { .. } => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "entry has no text content",
+        )),
+    }
+}
+
+fn normalize_url(url: &str) -> String {
+    if url.starts_with("www.") {
+        format!("https://{url}")
+    } else {
+        url.to_string()
+    }
+}
+
+/// The path of a file path entry, or the first file of a copied file list.
+fn first_path(entry: &ClipboardEntry) -> io::Result<PathBuf> {
+    let uri_list = entry
+        .representations
+        .iter()
+        .find(|representation| representation.mime == "text/uri-list")
+        .map(|representation| String::from_utf8_lossy(&representation.bytes).into_owned());
+    let text = match uri_list {
+        Some(uri_list) => uri_list,
+        None => entry_text(entry)?.to_string(),
+    };
+
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "entry has no path"))?;
+
+    if let Some(uri_path) = line.strip_prefix("file://") {
+        // Drop an optional host part, e.g. `file://localhost/home/...`.
+        let uri_path = &uri_path[uri_path.find('/').unwrap_or(0)..];
+        let decoded = urlencoding::decode(uri_path).map_err(io::Error::other)?;
+        return Ok(PathBuf::from(decoded.into_owned()));
+    }
+
+    match line.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory")),
+        None => Ok(PathBuf::from(line)),
+    }
+}
+
+/// Hands `target` to `xdg-open`, which goes through the OpenURI portal when sandboxed.
+fn open_with_default_handler(target: &str) -> io::Result<()> {
+    let mut child = Command::new("xdg-open").arg(target).spawn()?;
+    // Reap the child in the background so it doesn't linger as a zombie.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, mpsc};
+
+use wl_clipboard_rs::{copy, paste};
+
+use crate::services::clipboard::{ClipboardSource, WriteTarget};
+use crate::services::watcher::{self, WatchError};
+
+/// MIME types offered for plain text, in order of preference.
+const TEXT_MIMES: [&str; 5] = [
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+    "TEXT",
+];
+
+/// A MIME type to read or offer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mime {
+    /// Whichever plain text type the selection offers; offered under all of the common ones.
+    Text,
+    Specific(String),
+}
+
+/// Access to the system clipboard, so the capture and copy logic can run against a fake.
+pub trait ClipboardBackend: Send + Sync {
+    /// MIME types offered by the current selection.
+    fn offered_mime_types(&self, source: ClipboardSource) -> io::Result<HashSet<String>>;
+
+    /// Opens the selection's data in `mime`, returning it with the MIME type actually read.
+    fn read(&self, source: ClipboardSource, mime: &Mime) -> io::Result<(Box<dyn Read + Send>, String)>;
+
+    /// Takes over `target` with the given representations, first one preferred.
+    fn write(&self, target: WriteTarget, offers: Vec<(Mime, Vec<u8>)>) -> io::Result<()>;
+
+    /// Blocks and calls `on_change` for every new selection until it returns `false`.
+    fn watch(&self, on_change: Box<dyn FnMut(ClipboardSource) -> bool + Send>) -> Result<(), WatchError>;
+}
+
+/// The compositor's clipboard, through wl-clipboard-rs and the data-control watcher.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WaylandClipboard;
+
+fn paste_type(source: ClipboardSource) -> paste::ClipboardType {
+    match source {
+        ClipboardSource::Regular => paste::ClipboardType::Regular,
+        ClipboardSource::Primary => paste::ClipboardType::Primary,
+    }
+}
+
+fn copy_type(target: WriteTarget) -> copy::ClipboardType {
+    match target {
+        WriteTarget::Regular => copy::ClipboardType::Regular,
+        WriteTarget::Primary => copy::ClipboardType::Primary,
+        WriteTarget::Both => copy::ClipboardType::Both,
+    }
+}
+
+impl ClipboardBackend for WaylandClipboard {
+    fn offered_mime_types(&self, source: ClipboardSource) -> io::Result<HashSet<String>> {
+        paste::get_mime_types(paste_type(source), paste::Seat::Unspecified).map_err(io::Error::other)
+    }
+
+    fn read(&self, source: ClipboardSource, mime: &Mime) -> io::Result<(Box<dyn Read + Send>, String)> {
+        let mime = match mime {
+            Mime::Text => paste::MimeType::Text,
+            Mime::Specific(mime) => paste::MimeType::Specific(mime),
+        };
+        let (pipe, actual_mime) = paste::get_contents(paste_type(source), paste::Seat::Unspecified, mime)
+            .map_err(io::Error::other)?;
+        Ok((Box::new(pipe), actual_mime))
+    }
+
+    fn write(&self, target: WriteTarget, offers: Vec<(Mime, Vec<u8>)>) -> io::Result<()> {
+        let sources = offers
+            .into_iter()
+            .map(|(mime, bytes)| copy::MimeSource {
+                source: copy::Source::Bytes(bytes.into_boxed_slice()),
+                mime_type: match mime {
+                    Mime::Text => copy::MimeType::Text,
+                    Mime::Specific(mime) => copy::MimeType::Specific(mime),
+                },
+            })
+            .collect();
+
+        let mut opts = copy::Options::new();
+        opts.clipboard(copy_type(target));
+        opts.copy_multi(sources).map_err(io::Error::other)
+    }
+
+    fn watch(&self, on_change: Box<dyn FnMut(ClipboardSource) -> bool + Send>) -> Result<(), WatchError> {
+        watcher::watch_selection(on_change)
+    }
+}
+
+/// An in-memory clipboard for tests: selections are plain lists of `(mime, bytes)` offers.
+#[derive(Default)]
+pub struct MemoryClipboard {
+    selections: Mutex<HashMap<ClipboardSource, Vec<(String, Vec<u8>)>>>,
+    watchers: Mutex<Vec<mpsc::Sender<ClipboardSource>>>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the selection as if an application had copied `offers`.
+    pub fn offer(&self, source: ClipboardSource, offers: Vec<(String, Vec<u8>)>) {
+        if let Ok(mut selections) = self.selections.lock() {
+            selections.insert(source, offers);
+        }
+        if let Ok(mut watchers) = self.watchers.lock() {
+            watchers.retain(|watcher| watcher.send(source).is_ok());
+        }
+    }
+
+    pub fn offer_text(&self, source: ClipboardSource, text: &str) {
+        self.offer(source, vec![(TEXT_MIMES[0].to_string(), text.as_bytes().to_vec())]);
+    }
+
+    /// Empties the selection, as happens when its owner exits.
+    pub fn clear(&self, source: ClipboardSource) {
+        self.offer(source, Vec::new());
+    }
+
+    /// The offers currently held by the selection.
+    pub fn contents(&self, source: ClipboardSource) -> Vec<(String, Vec<u8>)> {
+        self.selections
+            .lock()
+            .ok()
+            .and_then(|selections| selections.get(&source).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn text(&self, source: ClipboardSource) -> Option<String> {
+        self.contents(source)
+            .into_iter()
+            .find(|(mime, _)| TEXT_MIMES.contains(&mime.as_str()))
+            .and_then(|(_, bytes)| String::from_utf8(bytes).ok())
+    }
+}
+
+impl fmt::Debug for MemoryClipboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryClipboard").finish_non_exhaustive()
+    }
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn offered_mime_types(&self, source: ClipboardSource) -> io::Result<HashSet<String>> {
+        Ok(self.contents(source).into_iter().map(|(mime, _)| mime).collect())
+    }
+
+    fn read(&self, source: ClipboardSource, mime: &Mime) -> io::Result<(Box<dyn Read + Send>, String)> {
+        let offers = self.contents(source);
+        let found = match mime {
+            Mime::Text => TEXT_MIMES
+                .iter()
+                .find_map(|text_mime| offers.iter().find(|(offered, _)| offered == text_mime)),
+            Mime::Specific(mime) => offers.iter().find(|(offered, _)| offered == mime),
+        };
+
+        match found {
+            Some((mime, bytes)) => Ok((Box::new(Cursor::new(bytes.clone())), mime.clone())),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "type not offered")),
+        }
+    }
+
+    fn write(&self, target: WriteTarget, offers: Vec<(Mime, Vec<u8>)>) -> io::Result<()> {
+        let offers: Vec<(String, Vec<u8>)> = offers
+            .into_iter()
+            .flat_map(|(mime, bytes)| match mime {
+                Mime::Text => TEXT_MIMES
+                    .iter()
+                    .map(|text_mime| (text_mime.to_string(), bytes.clone()))
+                    .collect::<Vec<_>>(),
+                Mime::Specific(mime) => vec![(mime, bytes)],
+            })
+            .collect();
+
+        match target {
+            WriteTarget::Regular => self.offer(ClipboardSource::Regular, offers),
+            WriteTarget::Primary => self.offer(ClipboardSource::Primary, offers),
+            WriteTarget::Both => {
+                self.offer(ClipboardSource::Regular, offers.clone());
+                self.offer(ClipboardSource::Primary, offers);
+            }
+        }
+        Ok(())
+    }
+
+    fn watch(&self, mut on_change: Box<dyn FnMut(ClipboardSource) -> bool + Send>) -> Result<(), WatchError> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut watchers) = self.watchers.lock() {
+            watchers.push(tx);
+        }
+        while let Ok(source) = rx.recv() {
+            if !on_change(source) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shared handle to the backend in use, the Wayland clipboard unless replaced.
+#[derive(Clone)]
+pub struct SharedBackend(Arc<dyn ClipboardBackend>);
+
+impl SharedBackend {
+    pub fn new(backend: Arc<dyn ClipboardBackend>) -> Self {
+        SharedBackend(backend)
+    }
+}
+
+impl Default for SharedBackend {
+    fn default() -> Self {
+        SharedBackend::new(Arc::new(WaylandClipboard))
+    }
+}
+
+impl Deref for SharedBackend {
+    type Target = dyn ClipboardBackend;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for SharedBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedBackend")
+    }
+}
@@ -1,23 +1,141 @@
+use std::collections::HashSet;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::io::{Cursor, Read};
+use std::time::SystemTime;
+use crate::config::Config;
 use crate::fl;
+use crate::services::backend::{ClipboardBackend, Mime};
+use uuid::Uuid;
+
+/// KeePassXC and other KDE-aware password managers offer this type with the value `secret`.
+const PASSWORD_MANAGER_HINT_MIME: &str = "x-kde-passwordManagerHint";
+/// The hint is a short marker; anything past this is not read from the offering application.
+const MAX_HINT_BYTES: u64 = 64;
+/// Markers for selections that must never be recorded (nspasteboard.org conventions).
+const CONCEALED_MIMES: [&str; 2] = [
+    "application/x-nspasteboard-concealed-type",
+    "org.nspasteboard.ConcealedType",
+];
+/// Markers for short-lived selections that are recorded but masked in the history.
+const TRANSIENT_MIMES: [&str; 2] = [
+    "application/x-nspasteboard-transient-type",
+    "org.nspasteboard.TransientType",
+];
+
+/// Marks selections re-offered by the applet itself so they are not captured again.
+const KEEP_ALIVE_MIME: &str = "application/x-clippy-land-keep-alive";
+
+/// Extra representations kept alongside the main text or image content, in offer order.
+const EXTRA_MIMES: [&str; 5] = [
+    "text/html",
+    "text/rtf",
+    "application/rtf",
+    "text/uri-list",
+    "x-special/gnome-copied-files",
+];
+/// Larger extra representations are dropped; the main content is still recorded.
+const MAX_REPRESENTATION_BYTES: usize = 1024 * 1024;
+
+/// What an entry looks like, used for its icon, `type:` searches and kind-specific actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentKind {
+    PlainText,
+    Url,
+    Email,
+    FilePath,
+    FileList,
+    Color,
+    Json,
+    Code,
+    Phone,
+    Image,
+}
+
+impl ContentKind {
+    /// Name used by the `type:` search operator and over D-Bus.
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentKind::PlainText => "plain",
+            ContentKind::Url => "url",
+            ContentKind::Email => "email",
+            ContentKind::FilePath => "path",
+            ContentKind::FileList => "files",
+            ContentKind::Color => "color",
+            ContentKind::Json => "json",
+            ContentKind::Code => "code",
+            ContentKind::Phone => "phone",
+            ContentKind::Image => "image",
+        }
+    }
+}
 
-use wl_clipboard_rs::{
-    copy::{MimeType as CopyMimeType, Options as CopyOptions, Source},
-    paste::{ClipboardType, MimeType as PasteMimeType, Seat, get_contents},
-};
+/// Which selection an entry was captured from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardSource {
+    /// The regular clipboard (Ctrl+C).
+    #[default]
+    Regular,
+    /// The primary selection, pasted with a middle click.
+    Primary,
+}
+
+/// Selections an entry is written back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteTarget {
+    Regular,
+    Primary,
+    Both,
+}
 
-const MAX_IMAGE_BYTES: usize = 8 * 1024 * 1024;
-const THUMBNAIL_SIZE_PX: u32 = 40;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensitivity {
+    Normal,
+    /// Recorded, but the content is masked in the popup and never written to disk.
+    Sensitive,
+    /// Not recorded at all.
+    Secret,
+}
 
 #[derive(Debug,Clone)]
 pub struct ClipboardEntry {
+    /// Stable identifier, kept across restarts and used by other programs to address the entry.
+    pub id: Uuid,
     pub title: String,
     pub content: ClipboardContent, // String or Image
+    /// Additional formats offered with the same selection, re-offered when copying from history.
+    pub representations: Vec<Representation>,
+    pub kind: ContentKind,
+    pub source: ClipboardSource,
     pub widget_id: cosmic::widget::Id,
     pub pinned: bool,
+    /// Name of the collection a pinned entry is grouped under.
+    pub collection: Option<String>,
+    /// Free-form tags in [`normalize_tag`] form, in the order they were added.
+    pub tags: Vec<String>,
     pub editing: bool,
+    pub sensitive: bool,
+    pub created_at: SystemTime,
+    pub last_copied_at: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Representation {
+    pub mime: String,
+    pub bytes: Vec<u8>,
+    pub hash: u64,
+}
+
+impl Representation {
+    pub fn new(mime: String, bytes: Vec<u8>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        mime.hash(&mut hasher);
+        bytes.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        Representation { mime, bytes, hash }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +146,9 @@ pub enum ClipboardContent {
         bytes: Vec<u8>,
         hash: u64,
         thumbnail_png: Option<Vec<u8>>,
+        /// Pixel dimensions, zero when the image could not be decoded.
+        width: u32,
+        height: u32,
     },
 }
 
@@ -50,7 +171,80 @@ pub enum ClipboardContent {
 //     pub editing: bool,
 // }
 
+impl ClipboardEntry {
+    pub fn new(title: String, content: ClipboardContent) -> Self {
+        let now = SystemTime::now();
+        ClipboardEntry {
+            id: Uuid::new_v4(),
+            kind: classify(&content, &[]),
+            source: ClipboardSource::Regular,
+            title,
+            content,
+            representations: Vec::new(),
+            widget_id: cosmic::widget::Id::unique(),
+            pinned: false,
+            collection: None,
+            tags: Vec::new(),
+            editing: false,
+            sensitive: false,
+            created_at: now,
+            last_copied_at: now,
+        }
+    }
+}
+
+impl ClipboardEntry {
+    /// Adds `tag` unless the entry already has it. Returns whether the tags changed.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        match normalize_tag(tag) {
+            Some(tag) if !self.tags.contains(&tag) => {
+                self.tags.push(tag);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        self.tags.len() != before
+    }
+}
+
+/// Lowercases a tag and drops a leading `#`, so `#SQL` and `sql` are the same tag.
+/// Returns `None` for empty tags and ones containing whitespace.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+    (!tag.is_empty() && !tag.contains(char::is_whitespace)).then(|| tag.to_lowercase())
+}
+
 impl ClipboardContent {
+    /// Builds an image entry from raw bytes, computing the hash and thumbnail.
+    pub fn image(mime: String, bytes: Vec<u8>, thumbnail_size_px: u32) -> Self {
+        let mut hasher = DefaultHasher::new();
+        mime.hash(&mut hasher);
+        bytes.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let decoded = decode_image(&mime, &bytes);
+        let (width, height) = decoded
+            .as_ref()
+            .map(|img| (img.width(), img.height()))
+            .unwrap_or_default();
+        let thumbnail_png = decoded.and_then(|img| encode_thumbnail_png(img, thumbnail_size_px));
+
+        ClipboardContent::Image {
+            mime,
+            bytes,
+            hash,
+            thumbnail_png,
+            width,
+            height,
+        }
+    }
+
     pub fn fingerprint(&self) -> ClipboardFingerprint {
         match self {
             ClipboardContent::Text(clipboard_content) => {
@@ -60,7 +254,7 @@ impl ClipboardContent {
                 mime,
                 bytes,
                 hash,
-                thumbnail_png: _,
+                ..
             } => ClipboardFingerprint::Image {
                 mime: mime.clone(),
                 bytes_len: bytes.len(),
@@ -105,38 +299,307 @@ pub enum ClipboardFingerprint {
     },
 }
 
-pub fn read_clipboard_entry() -> Option<ClipboardEntry> {
-    let clipboard_image = read_clipboard_image();
+pub fn read_clipboard_entry(
+    backend: &dyn ClipboardBackend,
+    config: &Config,
+    source: ClipboardSource,
+) -> Option<ClipboardEntry> {
+    let offered = read_offered_mime_types(backend, source);
+    if offered.contains(KEEP_ALIVE_MIME) {
+        // Our own copy of content that was already recorded when it was first offered.
+        return None;
+    }
+
+    let sensitivity = selection_sensitivity(backend, &offered, source);
+    if sensitivity == Sensitivity::Secret {
+        if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
+            eprintln!("[clippy-land] clipboard selection marked as secret, not recording it");
+        }
+        return None;
+    }
+
+    let mut entry = read_clipboard_content(backend, config, source)?;
+    entry.source = source;
+    entry.sensitive = sensitivity == Sensitivity::Sensitive;
+    entry.representations = read_extra_representations(backend, &offered, source);
+    entry.kind = classify(&entry.content, &entry.representations);
+    Some(entry)
+}
+
+fn read_extra_representations(
+    backend: &dyn ClipboardBackend,
+    offered: &HashSet<String>,
+    source: ClipboardSource,
+) -> Vec<Representation> {
+    EXTRA_MIMES
+        .into_iter()
+        .filter(|mime| offered.contains(*mime))
+        .filter_map(|mime| {
+            let (pipe, _) = backend.read(source, &Mime::Specific(mime.to_string())).ok()?;
+
+            let mut bytes = Vec::new();
+            pipe.take((MAX_REPRESENTATION_BYTES + 1) as u64)
+                .read_to_end(&mut bytes)
+                .ok()?;
+            if bytes.is_empty() || bytes.len() > MAX_REPRESENTATION_BYTES {
+                return None;
+            }
+
+            Some(Representation::new(mime.to_string(), bytes))
+        })
+        .collect()
+}
+
+fn read_clipboard_content(
+    backend: &dyn ClipboardBackend,
+    config: &Config,
+    source: ClipboardSource,
+) -> Option<ClipboardEntry> {
+    let clipboard_image = read_clipboard_image(backend, config, source);
 
     if clipboard_image.is_some() {
         let clipboard_image = clipboard_image.unwrap();
 
-        return Some(ClipboardEntry {
-            title: fl!("clipboard-image"),
-            content: clipboard_image,
-            widget_id: cosmic::widget::Id::unique(),
-            pinned: false,
-            editing: false,
-        });
+        return Some(ClipboardEntry::new(fl!("clipboard-image"), clipboard_image));
     }
 
-    let clipboard_text = read_clipboard_text();
+    let clipboard_text = read_clipboard_text(backend, source);
     if clipboard_text.is_some() {
         let clipboard_text = clipboard_text.unwrap();
 
-        return Some(ClipboardEntry {
-            title: summarize_one_line(&clipboard_text), 
-            content: ClipboardContent::Text(clipboard_text), 
-            widget_id: cosmic::widget::Id::unique(),
-            pinned: false,
-            editing: false,
-        })
+        return Some(ClipboardEntry::new(
+            summarize_one_line(&clipboard_text, config.title_max_chars),
+            ClipboardContent::Text(clipboard_text),
+        ))
     }
 
     None
 }
 
-fn summarize_one_line(text: &String) -> String {
+/// Guesses the kind of content from the text itself and the formats offered with it.
+pub fn classify(content: &ClipboardContent, representations: &[Representation]) -> ContentKind {
+    let text = match content {
+        ClipboardContent::Image { .. } => return ContentKind::Image,
+        ClipboardContent::Text(text) => text.trim(),
+    };
+
+    let offers_file_list = representations.iter().any(|representation| {
+        matches!(representation.mime.as_str(), "text/uri-list" | "x-special/gnome-copied-files")
+    });
+    if offers_file_list || (!text.is_empty() && text.lines().all(|line| line.trim().starts_with("file://"))) {
+        return ContentKind::FileList;
+    }
+
+    let single_line = !text.contains('\n');
+    if single_line && is_url(text) {
+        ContentKind::Url
+    } else if single_line && is_email(text) {
+        ContentKind::Email
+    } else if single_line && is_file_path(text) {
+        ContentKind::FilePath
+    } else if single_line && is_color(text) {
+        ContentKind::Color
+    } else if single_line && is_phone_number(text) {
+        ContentKind::Phone
+    } else if is_json(text) {
+        ContentKind::Json
+    } else if is_code(text) {
+        ContentKind::Code
+    } else {
+        ContentKind::PlainText
+    }
+}
+
+fn is_url(text: &str) -> bool {
+    if text.contains(char::is_whitespace) {
+        return false;
+    }
+    if text.starts_with("www.") && text.len() > 4 {
+        return true;
+    }
+
+    match text.split_once("://") {
+        Some((scheme, rest)) => {
+            !rest.is_empty()
+                && scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+fn is_email(text: &str) -> bool {
+    let address = text.strip_prefix("mailto:").unwrap_or(text);
+    if address.contains(char::is_whitespace) {
+        return false;
+    }
+
+    match address.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+        }
+        None => false,
+    }
+}
+
+fn is_file_path(text: &str) -> bool {
+    ((text.starts_with('/') && text.len() > 1) || text.starts_with("~/")) && !text.contains('\t')
+}
+
+fn is_color(text: &str) -> bool {
+    if let Some(hex) = text.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    let lower = text.to_ascii_lowercase();
+    ["rgb(", "rgba(", "hsl(", "hsla("]
+        .iter()
+        .any(|prefix| lower.starts_with(prefix))
+        && lower.ends_with(')')
+}
+
+/// Digit groups, the first optionally after a `+` and one optionally in parentheses, separated by
+/// spaces and at most one `-` or `.`. Dotted numbers only count with three or more groups of two
+/// or more digits, which leaves out decimals and IP addresses; ISO dates are left out as well.
+fn is_phone_number(text: &str) -> bool {
+    let rest = text.strip_prefix('+').unwrap_or(text);
+    if is_iso_date(rest) {
+        return false;
+    }
+
+    let mut chars = rest.chars().peekable();
+    let (mut groups, mut parenthesized, mut dotted) = (Vec::new(), 0, false);
+    loop {
+        let open = chars.next_if_eq(&'(').is_some();
+        let mut len = 0;
+        while chars.next_if(|c| c.is_ascii_digit()).is_some() {
+            len += 1;
+        }
+        if len == 0 || (open && chars.next_if_eq(&')').is_none()) {
+            return false;
+        }
+        groups.push(len);
+        parenthesized += usize::from(open);
+
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let (mut separator_len, mut punctuation) = (0, None);
+        while let Some(c) = chars.next_if(|&c| c == ' ' || (matches!(c, '-' | '.') && punctuation.is_none())) {
+            if c != ' ' {
+                punctuation = Some(c);
+            }
+            separator_len += 1;
+        }
+        // Only a closing parenthesis may be followed directly by the next group.
+        if separator_len == 0 && !open {
+            return false;
+        }
+        dotted |= punctuation == Some('.');
+    }
+
+    let digits: usize = groups.iter().sum();
+    (7..=15).contains(&digits)
+        && parenthesized <= 1
+        && (!dotted || (groups.len() >= 3 && groups.iter().all(|&len| len >= 2)))
+}
+
+/// `YYYY-MM-DD`
+fn is_iso_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(index, byte)| match index {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+fn is_json(text: &str) -> bool {
+    const MAX_JSON_BYTES: usize = 1024 * 1024;
+    (text.starts_with('{') || text.starts_with('['))
+        && text.len() <= MAX_JSON_BYTES
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+fn is_code(text: &str) -> bool {
+    const MARKERS: [&str; 12] = [
+        "fn ", "def ", "function ", "class ", "import ", "#include", "=>", "->", "::", "return ",
+        "const ", "let ",
+    ];
+
+    let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if lines.len() < 2 {
+        return false;
+    }
+
+    let code_lines = lines
+        .iter()
+        .filter(|line| {
+            line.ends_with(';')
+                || line.ends_with('{')
+                || line.ends_with('}')
+                || MARKERS.iter().any(|marker| line.contains(marker))
+        })
+        .count();
+
+    code_lines * 2 >= lines.len()
+}
+
+pub fn read_offered_mime_types(backend: &dyn ClipboardBackend, source: ClipboardSource) -> HashSet<String> {
+    match backend.offered_mime_types(source) {
+        Ok(mimes) => mimes,
+        Err(err) => {
+            if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
+                eprintln!("[clippy-land] clipboard mime types error: {err:?}");
+            }
+            HashSet::new()
+        }
+    }
+}
+
+/// Classifies a selection by the hint types password managers add to their offers.
+pub fn selection_sensitivity(
+    backend: &dyn ClipboardBackend,
+    offered: &HashSet<String>,
+    source: ClipboardSource,
+) -> Sensitivity {
+    if CONCEALED_MIMES.iter().any(|mime| offered.contains(*mime)) {
+        return Sensitivity::Secret;
+    }
+
+    if offered.contains(PASSWORD_MANAGER_HINT_MIME) {
+        // Anything other than an explicit `secret` still came from a password manager.
+        return match read_password_manager_hint(backend, source).as_deref() {
+            Some("secret") | None => Sensitivity::Secret,
+            Some(_) => Sensitivity::Sensitive,
+        };
+    }
+
+    if TRANSIENT_MIMES.iter().any(|mime| offered.contains(*mime)) {
+        return Sensitivity::Sensitive;
+    }
+
+    Sensitivity::Normal
+}
+
+fn read_password_manager_hint(backend: &dyn ClipboardBackend, source: ClipboardSource) -> Option<String> {
+    let (mut pipe, _) = backend
+        .read(source, &Mime::Specific(PASSWORD_MANAGER_HINT_MIME.to_string()))
+        .ok()?;
+
+    let mut hint = String::new();
+    pipe.take(MAX_HINT_BYTES).read_to_string(&mut hint).ok()?;
+    Some(hint.trim().to_string())
+}
+
+pub fn summarize_one_line(text: &String, max_chars: usize) -> String {
     let mut line = text
         .lines()
         .map(|line| line.trim_start())
@@ -144,27 +607,22 @@ fn summarize_one_line(text: &String) -> String {
         .unwrap_or("")
         .trim_end()
         .to_string();
-    const MAX_CHARS: usize = 25;
-    if line.chars().count() > MAX_CHARS {
-        line = line.chars().take(MAX_CHARS - 1).collect::<String>();
+    if line.chars().count() > max_chars {
+        line = line.chars().take(max_chars.saturating_sub(1)).collect::<String>();
         line.push('…');
     }
     line
 }
 
 
-pub fn read_clipboard_text() -> Option<String> {
-    let result = get_contents(
-        ClipboardType::Regular,
-        Seat::Unspecified,
-        PasteMimeType::Text,
-    );
+pub fn read_clipboard_text(backend: &dyn ClipboardBackend, source: ClipboardSource) -> Option<String> {
+    let result = backend.read(source, &Mime::Text);
 
     let (mut pipe, _) = match result {
         Ok(ok) => ok,
         Err(err) => {
             if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
-                eprintln!("[clippy-land] clipboard read get_contents error: {err:?}");
+                eprintln!("[clippy-land] clipboard read error: {err:?}");
             }
             return None;
         }
@@ -191,16 +649,18 @@ pub fn read_clipboard_text() -> Option<String> {
     (!text.is_empty()).then_some(text)
 }
 
-pub fn read_clipboard_image() -> Option<ClipboardContent> {
+pub fn read_clipboard_image(
+    backend: &dyn ClipboardBackend,
+    config: &Config,
+    source: ClipboardSource,
+) -> Option<ClipboardContent> {
+    let max_image_bytes = config.max_image_bytes;
+
     // Try common image formats first.
     const IMAGE_MIMES: [&str; 3] = ["image/png", "image/jpeg", "image/webp"];
 
     for mime in IMAGE_MIMES {
-        let result = get_contents(
-            ClipboardType::Regular,
-            Seat::Unspecified,
-            PasteMimeType::Specific(mime),
-        );
+        let result = backend.read(source, &Mime::Specific(mime.to_string()));
 
         let (pipe, actual_mime) = match result {
             Ok(ok) => ok,
@@ -208,16 +668,16 @@ pub fn read_clipboard_image() -> Option<ClipboardContent> {
         };
 
         let mut bytes = Vec::new();
-        let mut limited = pipe.take((MAX_IMAGE_BYTES + 1) as u64);
+        let mut limited = pipe.take((max_image_bytes + 1) as u64);
         if limited.read_to_end(&mut bytes).is_err() {
             continue;
         }
-        if bytes.len() > MAX_IMAGE_BYTES {
+        if bytes.len() > max_image_bytes {
             if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
                 eprintln!(
                     "[clippy-land] clipboard image ignored (too large): {} bytes (max {})",
                     bytes.len(),
-                    MAX_IMAGE_BYTES
+                    max_image_bytes
                 );
             }
             continue;
@@ -226,58 +686,89 @@ pub fn read_clipboard_image() -> Option<ClipboardContent> {
             continue;
         }
 
-        let mut hasher = DefaultHasher::new();
-        actual_mime.hash(&mut hasher);
-        bytes.hash(&mut hasher);
-        let hash = hasher.finish();
-
-        let thumbnail_png = make_thumbnail_png(&actual_mime, &bytes);
-
-        return Some(ClipboardContent::Image {
-            mime: actual_mime,
-            bytes,
-            hash,
-            thumbnail_png,
-        });
+        return Some(ClipboardContent::image(actual_mime, bytes, config.thumbnail_size_px));
     }
 
     None
 }
 
-fn make_thumbnail_png(mime: &str, bytes: &[u8]) -> Option<Vec<u8>> {
+fn decode_image(mime: &str, bytes: &[u8]) -> Option<image::DynamicImage> {
     let format = match mime {
         "image/png" => image::ImageFormat::Png,
         "image/jpeg" => image::ImageFormat::Jpeg,
         "image/webp" => image::ImageFormat::WebP,
         _ => {
             // Let the decoder guess if we don't recognize the exact mime.
-            return image::load_from_memory(bytes)
-                .ok()
-                .and_then(|img| encode_thumbnail_png(img));
+            return image::load_from_memory(bytes).ok();
         }
     };
 
-    let decoded = image::load_from_memory_with_format(bytes, format)
+    image::load_from_memory_with_format(bytes, format)
         .or_else(|_| image::load_from_memory(bytes))
-        .ok()?;
-
-    encode_thumbnail_png(decoded)
+        .ok()
 }
 
-fn encode_thumbnail_png(decoded: image::DynamicImage) -> Option<Vec<u8>> {
-    let thumb = decoded.thumbnail(THUMBNAIL_SIZE_PX, THUMBNAIL_SIZE_PX);
+fn encode_thumbnail_png(decoded: image::DynamicImage, size_px: u32) -> Option<Vec<u8>> {
+    let thumb = decoded.thumbnail(size_px, size_px);
     let mut out = Vec::new();
     let mut cursor = Cursor::new(&mut out);
     thumb.write_to(&mut cursor, image::ImageFormat::Png).ok()?;
     Some(out)
 }
 
-pub fn write_clipboard_text(text: &str) -> bool {
-    let opts = CopyOptions::new();
-    match opts.copy(
-        Source::Bytes(text.as_bytes().to_vec().into()),
-        CopyMimeType::Autodetect,
-    ) {
+/// Offers the entry's main content together with all of its extra representations.
+pub fn write_clipboard_entry(backend: &dyn ClipboardBackend, entry: &ClipboardEntry) -> bool {
+    write_clipboard_entry_to(backend, entry, WriteTarget::Regular)
+}
+
+/// Takes over the regular selection with the entry's content, so it survives the source
+/// application exiting. The offer is marked so the watcher does not record it a second time.
+///
+/// Only the main content and the `EXTRA_MIMES` representations are offered again; any other
+/// format the source offered is gone once it exits.
+pub fn keep_clipboard_alive(backend: &dyn ClipboardBackend, entry: &ClipboardEntry) -> bool {
+    let marker = (Mime::Specific(KEEP_ALIVE_MIME.to_string()), Vec::new());
+    write_offers(backend, entry_offers(entry).chain([marker]).collect(), WriteTarget::Regular)
+}
+
+/// Like [`write_clipboard_entry`], to the primary selection, the regular clipboard or both.
+pub fn write_clipboard_entry_to(
+    backend: &dyn ClipboardBackend,
+    entry: &ClipboardEntry,
+    target: WriteTarget,
+) -> bool {
+    write_offers(backend, entry_offers(entry).collect(), target)
+}
+
+/// The entry's main content followed by its extra representations.
+fn entry_offers(entry: &ClipboardEntry) -> impl Iterator<Item = (Mime, Vec<u8>)> + '_ {
+    let main = match &entry.content {
+        ClipboardContent::Text(text) => (Mime::Text, text.as_bytes().to_vec()),
+        ClipboardContent::Image { mime, bytes, .. } => (Mime::Specific(mime.clone()), bytes.clone()),
+    };
+
+    std::iter::once(main).chain(
+        entry
+            .representations
+            .iter()
+            .map(|representation| (Mime::Specific(representation.mime.clone()), representation.bytes.clone())),
+    )
+}
+
+fn write_offers(backend: &dyn ClipboardBackend, offers: Vec<(Mime, Vec<u8>)>, target: WriteTarget) -> bool {
+    match backend.write(target, offers) {
+        Ok(()) => true,
+        Err(err) => {
+            if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
+                eprintln!("[clippy-land] clipboard multi write error: {err:?}");
+            }
+            false
+        }
+    }
+}
+
+pub fn write_clipboard_text(backend: &dyn ClipboardBackend, text: &str) -> bool {
+    match backend.write(WriteTarget::Regular, vec![(Mime::Text, text.as_bytes().to_vec())]) {
         Ok(()) => true,
         Err(err) => {
             if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
@@ -288,12 +779,8 @@ pub fn write_clipboard_text(text: &str) -> bool {
     }
 }
 
-pub fn write_clipboard_image(mime: &str, bytes: &[u8]) -> bool {
-    let opts = CopyOptions::new();
-    match opts.copy(
-        Source::Bytes(bytes.to_vec().into_boxed_slice()),
-        CopyMimeType::Specific(mime.to_string()),
-    ) {
+pub fn write_clipboard_image(backend: &dyn ClipboardBackend, mime: &str, bytes: &[u8]) -> bool {
+    match backend.write(WriteTarget::Regular, vec![(Mime::Specific(mime.to_string()), bytes.to_vec())]) {
         Ok(()) => true,
         Err(err) => {
             if std::env::var_os("CLIPPY_LAND_DEBUG_CLIPBOARD").is_some() {
@@ -303,3 +790,54 @@ pub fn write_clipboard_image(mime: &str, bytes: &[u8]) -> bool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind_of(text: &str) -> ContentKind {
+        classify(&ClipboardContent::Text(text.to_string()), &[])
+    }
+
+    #[test]
+    fn phone_numbers() {
+        for text in ["+1 (555) 123-4567", "555-123-4567", "+49 30 1234567", "(030) 1234567", "555.123.4567"] {
+            assert_eq!(kind_of(text), ContentKind::Phone, "{text}");
+        }
+    }
+
+    #[test]
+    fn numbers_that_are_not_phone_numbers() {
+        for text in ["2026-10-18", "3.14159265", "10.0.0.1 255.255.255.0", "555--1234567", "12 34", "(555 123-4567"] {
+            assert_ne!(kind_of(text), ContentKind::Phone, "{text}");
+        }
+    }
+
+    #[test]
+    fn urls_and_emails() {
+        assert_eq!(kind_of("https://example.com/path?q=1"), ContentKind::Url);
+        assert_eq!(kind_of("www.example.com"), ContentKind::Url);
+        assert_eq!(kind_of("someone@example.com"), ContentKind::Email);
+        assert_eq!(kind_of("mailto:someone@example.com"), ContentKind::Email);
+        assert_eq!(kind_of("someone@localhost"), ContentKind::PlainText);
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(kind_of(r#"{"name": "clippy", "tags": [1, 2]}"#), ContentKind::Json);
+        assert_eq!(kind_of("[1, 2, 3]"), ContentKind::Json);
+        assert_eq!(kind_of("{not json}"), ContentKind::PlainText);
+    }
+
+    #[test]
+    fn only_the_start_of_the_password_manager_hint_is_read() {
+        let backend = crate::services::backend::MemoryClipboard::new();
+        let mut hint = b"secret".to_vec();
+        hint.resize(1024 * 1024, b' ');
+        backend.offer(ClipboardSource::Regular, vec![(PASSWORD_MANAGER_HINT_MIME.to_string(), hint)]);
+        let offered = HashSet::from([PASSWORD_MANAGER_HINT_MIME.to_string()]);
+
+        assert_eq!(read_password_manager_hint(&backend, ClipboardSource::Regular).as_deref(), Some("secret"));
+        assert_eq!(selection_sensitivity(&backend, &offered, ClipboardSource::Regular), Sensitivity::Secret);
+    }
+}
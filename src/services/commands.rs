@@ -0,0 +1,175 @@
+use std::fmt;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::fl;
+
+/// How the entry text is handed to a custom action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputMode {
+    #[default]
+    Stdin,
+    /// Appended to the command line as its last, quoted argument.
+    Argument,
+}
+
+/// What happens with a custom action's standard output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OutputMode {
+    #[default]
+    ReplaceClipboard,
+    NewEntry,
+    Discard,
+}
+
+/// A user-defined command offered in the entry menu, e.g. `jq .` or a ticket link script.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CustomAction {
+    pub name: String,
+    /// Shell command line, run with `sh -c`.
+    pub command: String,
+    #[serde(default)]
+    pub input: InputMode,
+    #[serde(default)]
+    pub output: OutputMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Spawn(String),
+    TimedOut(u64),
+    /// Non-zero exit, with the first line of stderr.
+    Failed(String),
+    NotText,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            CommandError::Spawn(reason) => fl!("command-spawn-failed", reason = reason.as_str()),
+            CommandError::TimedOut(secs) => fl!("command-timed-out", secs = secs),
+            CommandError::Failed(reason) => fl!("command-failed", reason = reason.as_str()),
+            CommandError::NotText => fl!("command-not-text"),
+        };
+        f.write_str(&message)
+    }
+}
+
+/// Runs `action` on `text` and returns its standard output, minus the one trailing newline most
+/// tools end their output with.
+///
+/// The process is killed once `timeout` passes.
+pub async fn run_custom_action(
+    action: CustomAction,
+    text: String,
+    timeout: Duration,
+) -> Result<String, CommandError> {
+    let mut command = Command::new("sh");
+    match action.input {
+        InputMode::Stdin => {
+            command.arg("-c").arg(&action.command).stdin(Stdio::piped());
+        }
+        InputMode::Argument => {
+            command
+                .arg("-c")
+                .arg(format!("{} \"$1\"", action.command))
+                .arg("clippy-land")
+                .arg(&text)
+                .stdin(Stdio::null());
+        }
+    }
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command
+        .spawn()
+        .map_err(|err| CommandError::Spawn(err.to_string()))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Written concurrently so a command producing output before reading all input can't stall.
+        // A command that exits without reading its input is not an error.
+        tokio::spawn(async move {
+            _ = stdin.write_all(text.as_bytes()).await;
+        });
+    }
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| CommandError::TimedOut(timeout.as_secs()))?
+        .map_err(|err| CommandError::Spawn(err.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| output.status.to_string());
+        return Err(CommandError::Failed(reason));
+    }
+
+    let mut stdout = String::from_utf8(output.stdout).map_err(|_| CommandError::NotText)?;
+    if stdout.ends_with('\n') {
+        stdout.pop();
+        if stdout.ends_with('\r') {
+            stdout.pop();
+        }
+    }
+    Ok(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn action(command: &str, input: InputMode) -> CustomAction {
+        CustomAction {
+            name: "test".into(),
+            command: command.into(),
+            input,
+            output: OutputMode::ReplaceClipboard,
+        }
+    }
+
+    #[tokio::test]
+    async fn text_is_passed_on_stdin_or_as_an_argument() {
+        let stdin = run_custom_action(action("cat", InputMode::Stdin), "a \"b\" $HOME".into(), TIMEOUT).await;
+        assert_eq!(stdin.as_deref(), Ok("a \"b\" $HOME"));
+
+        let argument = run_custom_action(action("printf '%s'", InputMode::Argument), "a \"b\" $HOME".into(), TIMEOUT).await;
+        assert_eq!(argument.as_deref(), Ok("a \"b\" $HOME"));
+    }
+
+    #[tokio::test]
+    async fn only_one_trailing_newline_is_trimmed() {
+        let output = run_custom_action(action("tr a-z A-Z", InputMode::Stdin), "one\ntwo\n\n".into(), TIMEOUT).await;
+        assert_eq!(output.as_deref(), Ok("ONE\nTWO\n"));
+
+        let output = run_custom_action(action("printf 'x\\r\\n'", InputMode::Stdin), String::new(), TIMEOUT).await;
+        assert_eq!(output.as_deref(), Ok("x"));
+    }
+
+    #[tokio::test]
+    async fn a_non_zero_exit_reports_stderr() {
+        let output = run_custom_action(action("echo 'bad input' >&2; exit 3", InputMode::Stdin), String::new(), TIMEOUT).await;
+        assert_eq!(output, Err(CommandError::Failed("bad input".into())));
+
+        let output = run_custom_action(action("exit 3", InputMode::Stdin), String::new(), TIMEOUT).await;
+        assert!(matches!(output, Err(CommandError::Failed(reason)) if reason.contains('3')));
+    }
+
+    #[tokio::test]
+    async fn slow_commands_time_out() {
+        let output = run_custom_action(action("sleep 5", InputMode::Stdin), String::new(), Duration::from_millis(100)).await;
+        assert_eq!(output, Err(CommandError::TimedOut(0)));
+    }
+}
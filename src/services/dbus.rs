@@ -0,0 +1,253 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Type;
+
+use crate::services::clipboard::{ClipboardContent, ClipboardEntry};
+
+pub const BUS_NAME: &str = "com.keewee.CosmicAppletClippyLand1";
+pub const OBJECT_PATH: &str = "/com/keewee/CosmicAppletClippyLand1";
+
+/// Entry metadata as sent over the bus. Content is only returned by `Get`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct EntryInfo {
+    pub id: String,
+    /// Empty for hidden entries.
+    pub title: String,
+    /// See [`ContentKind::name`](crate::services::clipboard::ContentKind::name).
+    pub kind: String,
+    pub mime: String,
+    pub pinned: bool,
+    pub sensitive: bool,
+    /// Seconds since the unix epoch.
+    pub created_at: u64,
+    /// Seconds since the unix epoch.
+    pub last_copied_at: u64,
+}
+
+impl EntryInfo {
+    pub fn from_entry(entry: &ClipboardEntry) -> Self {
+        let unix_secs = |time: std::time::SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        };
+
+        EntryInfo {
+            id: entry.id.to_string(),
+            title: if entry.sensitive { String::new() } else { entry.title.clone() },
+            kind: entry.kind.name().to_string(),
+            mime: match &entry.content {
+                ClipboardContent::Text(_) => "text/plain;charset=utf-8".to_string(),
+                ClipboardContent::Image { mime, .. } => mime.clone(),
+            },
+            pinned: entry.pinned,
+            sensitive: entry.sensitive,
+            created_at: unix_secs(entry.created_at),
+            last_copied_at: unix_secs(entry.last_copied_at),
+        }
+    }
+}
+
+/// One-shot reply slot that can travel inside a `Message`, which must be `Clone`.
+pub struct Responder<T>(Arc<Mutex<Option<oneshot::Sender<T>>>>);
+
+impl<T> Responder<T> {
+    fn new() -> (Self, oneshot::Receiver<T>) {
+        let (tx, rx) = oneshot::channel();
+        (Responder(Arc::new(Mutex::new(Some(tx)))), rx)
+    }
+
+    /// Sends the reply; only the first call on any clone has an effect.
+    pub fn respond(&self, value: T) {
+        let sender = self.0.lock().ok().and_then(|mut sender| sender.take());
+        if let Some(sender) = sender {
+            _ = sender.send(value);
+        }
+    }
+}
+
+impl<T> Clone for Responder<T> {
+    fn clone(&self) -> Self {
+        Responder(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for Responder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Responder")
+    }
+}
+
+/// A bus call forwarded to the applet's update loop, answered through its [`Responder`].
+///
+/// Ids are the entries' UUIDs in their hyphenated text form.
+#[derive(Debug, Clone)]
+pub enum Request {
+    List(Responder<Vec<EntryInfo>>),
+    Get(String, Responder<Option<(EntryInfo, Vec<u8>)>>),
+    Copy(String, Responder<bool>),
+    Remove(String, Responder<bool>),
+    Pin(String, Responder<bool>),
+    Unpin(String, Responder<bool>),
+    Clear(Responder<()>),
+    /// Records a text entry as if it had been copied; replies with its id.
+    Add(String, String, Responder<Option<String>>),
+    Search(String, Responder<Result<Vec<EntryInfo>, String>>),
+}
+
+impl Request {
+    /// Whether handling the request may change the persisted history.
+    pub fn mutates_history(&self) -> bool {
+        matches!(
+            self,
+            Request::Remove(..)
+                | Request::Pin(..)
+                | Request::Unpin(..)
+                | Request::Clear(_)
+                | Request::Add(..)
+        )
+    }
+}
+
+struct HistoryInterface {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+impl HistoryInterface {
+    async fn call<T>(&self, request: impl FnOnce(Responder<T>) -> Request) -> fdo::Result<T> {
+        let (responder, reply) = Responder::new();
+        self.requests
+            .send(request(responder))
+            .map_err(|_| fdo::Error::Failed("the applet is shutting down".to_string()))?;
+        reply
+            .await
+            .map_err(|_| fdo::Error::Failed("the request was dropped".to_string()))
+    }
+
+    fn found(found: bool, id: &str) -> fdo::Result<()> {
+        if found {
+            Ok(())
+        } else {
+            Err(fdo::Error::InvalidArgs(format!("no entry with id {id}")))
+        }
+    }
+}
+
+#[zbus::interface(name = "com.keewee.CosmicAppletClippyLand1")]
+impl HistoryInterface {
+    /// All entries, pinned first, newest first.
+    async fn list(&self) -> fdo::Result<Vec<EntryInfo>> {
+        self.call(Request::List).await
+    }
+
+    /// The entry and its content: UTF-8 text, or the image bytes in the entry's mime type.
+    async fn get(&self, id: String) -> fdo::Result<(EntryInfo, Vec<u8>)> {
+        self.call(|responder| Request::Get(id.clone(), responder))
+            .await?
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no visible entry with id {id}")))
+    }
+
+    /// Places the entry on the clipboard. Hidden entries can't be copied this way.
+    async fn copy(&self, id: String) -> fdo::Result<()> {
+        let found = self.call(|responder| Request::Copy(id.clone(), responder)).await?;
+        found
+            .then_some(())
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no visible entry with id {id}")))
+    }
+
+    async fn remove(&self, id: String) -> fdo::Result<()> {
+        let found = self.call(|responder| Request::Remove(id.clone(), responder)).await?;
+        Self::found(found, &id)
+    }
+
+    async fn pin(&self, id: String) -> fdo::Result<()> {
+        let found = self.call(|responder| Request::Pin(id.clone(), responder)).await?;
+        Self::found(found, &id)
+    }
+
+    async fn unpin(&self, id: String) -> fdo::Result<()> {
+        let found = self.call(|responder| Request::Unpin(id.clone(), responder)).await?;
+        Self::found(found, &id)
+    }
+
+    /// Removes all unpinned entries.
+    async fn clear(&self) -> fdo::Result<()> {
+        self.call(Request::Clear).await
+    }
+
+    /// Adds a text entry with the given title, or a generated one when empty, and returns its id.
+    async fn add(&self, title: String, text: String) -> fdo::Result<String> {
+        if text.trim().is_empty() {
+            return Err(fdo::Error::InvalidArgs("the text is empty".to_string()));
+        }
+        self.call(|responder| Request::Add(title, text, responder))
+            .await?
+            .ok_or_else(|| fdo::Error::Failed("the entry was not recorded".to_string()))
+    }
+
+    /// Entries matching a query in the search box syntax, best match first.
+    async fn search(&self, query: String) -> fdo::Result<Vec<EntryInfo>> {
+        self.call(|responder| Request::Search(query, responder))
+            .await?
+            .map_err(fdo::Error::InvalidArgs)
+    }
+
+    #[zbus(signal)]
+    async fn entry_added(emitter: &SignalEmitter<'_>, entry: EntryInfo) -> zbus::Result<()>;
+}
+
+/// Client side of the interface, used by the `clippy-land` command-line tool.
+#[zbus::proxy(
+    interface = "com.keewee.CosmicAppletClippyLand1",
+    default_service = "com.keewee.CosmicAppletClippyLand1",
+    default_path = "/com/keewee/CosmicAppletClippyLand1",
+    gen_blocking = false
+)]
+pub trait History {
+    fn list(&self) -> zbus::Result<Vec<EntryInfo>>;
+    fn get(&self, id: &str) -> zbus::Result<(EntryInfo, Vec<u8>)>;
+    fn copy(&self, id: &str) -> zbus::Result<()>;
+    fn remove(&self, id: &str) -> zbus::Result<()>;
+    fn pin(&self, id: &str) -> zbus::Result<()>;
+    fn unpin(&self, id: &str) -> zbus::Result<()>;
+    fn clear(&self) -> zbus::Result<()>;
+    fn add(&self, title: &str, text: &str) -> zbus::Result<String>;
+    fn search(&self, query: &str) -> zbus::Result<Vec<EntryInfo>>;
+
+    #[zbus(signal)]
+    fn entry_added(&self, entry: EntryInfo) -> zbus::Result<()>;
+}
+
+/// Claims [`BUS_NAME`] on the session bus and forwards incoming calls to `requests`.
+pub async fn serve(requests: mpsc::UnboundedSender<Request>) -> zbus::Result<zbus::Connection> {
+    serve_on(zbus::connection::Builder::session()?.name(BUS_NAME)?, requests).await
+}
+
+/// Like [`serve`], on any connection, e.g. one end of a peer-to-peer pair in tests. Claiming a
+/// bus name is up to the caller.
+pub async fn serve_on(
+    builder: zbus::connection::Builder<'_>,
+    requests: mpsc::UnboundedSender<Request>,
+) -> zbus::Result<zbus::Connection> {
+    builder
+        .serve_at(OBJECT_PATH, HistoryInterface { requests })?
+        .build()
+        .await
+}
+
+/// Emits `EntryAdded` for a newly recorded entry.
+pub async fn emit_entry_added(connection: zbus::Connection, entry: EntryInfo) {
+    let result = match SignalEmitter::new(&connection, OBJECT_PATH) {
+        Ok(emitter) => HistoryInterface::entry_added(&emitter, entry).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        eprintln!("[clippy-land] failed to emit EntryAdded: {err}");
+    }
+}
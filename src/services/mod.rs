@@ -1 +1,13 @@
+pub mod actions;
+pub mod backend;
 pub mod clipboard;
+pub mod commands;
+pub mod dbus;
+pub mod query;
+pub mod retention;
+pub mod search;
+pub mod snippets;
+pub mod storage;
+pub mod template;
+pub mod transform;
+pub mod watcher;
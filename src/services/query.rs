@@ -0,0 +1,387 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use chrono::{Days, Local, NaiveDate};
+use regex::Regex;
+
+use crate::fl;
+use crate::services::clipboard::{self, ClipboardContent, ClipboardEntry, ClipboardSource, ContentKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// A reserved operator such as `in:` without a value.
+    MissingValue(String),
+    UnknownType(String),
+    UnknownFlag(String),
+    InvalidDate(String),
+    InvalidLength(String),
+    InvalidRegex(String),
+    InvalidTag(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            QueryError::MissingValue(value) => fl!("query-missing-value", value = value.as_str()),
+            QueryError::UnknownType(value) => fl!("query-unknown-type", value = value.as_str()),
+            QueryError::UnknownFlag(value) => fl!("query-unknown-flag", value = value.as_str()),
+            QueryError::InvalidDate(value) => fl!("query-invalid-date", value = value.as_str()),
+            QueryError::InvalidLength(value) => fl!("query-invalid-length", value = value.as_str()),
+            QueryError::InvalidRegex(value) => fl!("query-invalid-regex", value = value.as_str()),
+            QueryError::InvalidTag(value) => fl!("query-invalid-tag", value = value.as_str()),
+        };
+        f.write_str(&message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryType {
+    /// Any text entry, whatever its kind.
+    Text,
+    Kind(ContentKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, value: usize, bound: usize) -> bool {
+        match self {
+            Comparison::Less => value < bound,
+            Comparison::LessOrEqual => value <= bound,
+            Comparison::Equal => value == bound,
+            Comparison::GreaterOrEqual => value >= bound,
+            Comparison::Greater => value > bound,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Type(EntryType),
+    Pinned,
+    /// Captured from the primary selection.
+    Primary,
+    /// In the collection with this [`collection_key`].
+    Collection(String),
+    /// Tagged with this tag, in [`clipboard::normalize_tag`] form.
+    Tag(String),
+    /// Last copied at or after the start of the given day.
+    After(SystemTime),
+    /// Last copied before the start of the given day.
+    Before(SystemTime),
+    Length(Comparison, usize),
+    Regex(Regex),
+    /// A negated plain word, matched case-insensitively as a substring.
+    Contains(String),
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    negated: bool,
+    filter: Filter,
+}
+
+/// A parsed search box query: operator filters plus the remaining free text.
+///
+/// Supported operators are `type:<kind>` (see [`ContentKind`]), `is:pinned`, `is:primary`,
+/// `in:<collection>`, `tag:<tag>` or `#<tag>`, `after:<date>`, `before:<date>` (`YYYY-MM-DD`, `today` or `yesterday`),
+/// `len:<op><n>` and `/regex/`.
+/// Any term can be negated with a leading `-`.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+    text: String,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut query = Query::default();
+        let mut words = Vec::new();
+
+        for token in tokenize(input) {
+            let (negated, body) = match token.strip_prefix('-') {
+                Some(body) if !body.is_empty() => (true, body),
+                _ => (false, token.as_str()),
+            };
+
+            let filter = match parse_filter(body)? {
+                Some(filter) => filter,
+                None if negated => Filter::Contains(body.to_lowercase()),
+                None => {
+                    words.push(body.to_string());
+                    continue;
+                }
+            };
+            query.terms.push(Term { negated, filter });
+        }
+
+        query.text = words.join(" ");
+        Ok(query)
+    }
+
+    /// Free text left after removing operators, used for fuzzy ranking.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.text.trim().is_empty()
+    }
+
+    /// Whether `entry` passes every operator. Sensitive entries never match a non-empty query.
+    pub fn matches(&self, entry: &ClipboardEntry) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        if entry.sensitive {
+            return false;
+        }
+
+        self.terms
+            .iter()
+            .all(|term| filter_matches(&term.filter, entry) != term.negated)
+    }
+}
+
+/// Splits on whitespace, keeping `/regex with spaces/` together. A regex only closes at a `/`
+/// that ends a word, so `/usr` or `/usr/bin` stay plain words.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut token = String::new();
+        if c == '-' {
+            token.push(c);
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'/') {
+            let mut regex = chars.clone();
+            let mut pattern = String::new();
+            pattern.push('/');
+            regex.next();
+            let mut escaped = false;
+            while let Some(c) = regex.next() {
+                pattern.push(c);
+                if c == '/' && !escaped && regex.peek().is_none_or(|next| next.is_whitespace()) {
+                    token.push_str(&pattern);
+                    chars = regex;
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+        }
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            token.push(c);
+            chars.next();
+        }
+
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Returns `None` for plain words.
+fn parse_filter(body: &str) -> Result<Option<Filter>, QueryError> {
+    if let Some(pattern) = body.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+        return Regex::new(pattern)
+            .map(|regex| Some(Filter::Regex(regex)))
+            .map_err(|_| QueryError::InvalidRegex(pattern.to_string()));
+    }
+
+    // A lone `#` is left as a plain word.
+    if let Some(tag) = body.strip_prefix('#').and_then(clipboard::normalize_tag) {
+        return Ok(Some(Filter::Tag(tag)));
+    }
+
+    let Some((operator, value)) = body.split_once(':') else {
+        return Ok(None);
+    };
+    let value = value.trim();
+
+    let filter = match operator.to_lowercase().as_str() {
+        "type" => Filter::Type(match value.to_lowercase().as_str() {
+            "text" => EntryType::Text,
+            "plain" => EntryType::Kind(ContentKind::PlainText),
+            "image" => EntryType::Kind(ContentKind::Image),
+            "url" | "link" => EntryType::Kind(ContentKind::Url),
+            "email" | "mail" => EntryType::Kind(ContentKind::Email),
+            "path" => EntryType::Kind(ContentKind::FilePath),
+            "files" => EntryType::Kind(ContentKind::FileList),
+            "color" | "colour" => EntryType::Kind(ContentKind::Color),
+            "json" => EntryType::Kind(ContentKind::Json),
+            "code" => EntryType::Kind(ContentKind::Code),
+            "phone" => EntryType::Kind(ContentKind::Phone),
+            _ => return Err(QueryError::UnknownType(value.to_string())),
+        }),
+        "is" => match value.to_lowercase().as_str() {
+            "pinned" => Filter::Pinned,
+            "primary" => Filter::Primary,
+            _ => return Err(QueryError::UnknownFlag(value.to_string())),
+        },
+        "in" if value.is_empty() => return Err(QueryError::MissingValue(operator.to_string())),
+        "in" => Filter::Collection(collection_key(value)),
+        "tag" => match clipboard::normalize_tag(value) {
+            Some(tag) => Filter::Tag(tag),
+            None => return Err(QueryError::InvalidTag(value.to_string())),
+        },
+        "after" => Filter::After(parse_day(value)?),
+        "before" => Filter::Before(parse_day(value)?),
+        "len" => {
+            let (comparison, bound) = parse_length(value)
+                .ok_or_else(|| QueryError::InvalidLength(value.to_string()))?;
+            Filter::Length(comparison, bound)
+        }
+        // Only the operators above are reserved: `note:`, `10:30` or `http://…` are plain words.
+        _ => return Ok(None),
+    };
+
+    Ok(Some(filter))
+}
+
+/// Case-insensitive form of a collection name in which whitespace is written as `-`, so
+/// `in:sql-fragments` finds the "SQL fragments" collection.
+pub fn collection_key(name: &str) -> String {
+    name.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Start of the given local day.
+fn parse_day(value: &str) -> Result<SystemTime, QueryError> {
+    let today = Local::now().date_naive();
+    let day = match value.to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.checked_sub_days(Days::new(1)),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    };
+
+    day.and_then(|day| day.and_hms_opt(0, 0, 0))
+        .and_then(|start| start.and_local_timezone(Local).earliest())
+        .map(SystemTime::from)
+        .ok_or_else(|| QueryError::InvalidDate(value.to_string()))
+}
+
+fn parse_length(value: &str) -> Option<(Comparison, usize)> {
+    let (comparison, number) = if let Some(rest) = value.strip_prefix(">=") {
+        (Comparison::GreaterOrEqual, rest)
+    } else if let Some(rest) = value.strip_prefix("<=") {
+        (Comparison::LessOrEqual, rest)
+    } else if let Some(rest) = value.strip_prefix('>') {
+        (Comparison::Greater, rest)
+    } else if let Some(rest) = value.strip_prefix('<') {
+        (Comparison::Less, rest)
+    } else if let Some(rest) = value.strip_prefix('=') {
+        (Comparison::Equal, rest)
+    } else {
+        (Comparison::Equal, value)
+    };
+
+    number.parse().ok().map(|bound| (comparison, bound))
+}
+
+fn filter_matches(filter: &Filter, entry: &ClipboardEntry) -> bool {
+    match (filter, &entry.content) {
+        (Filter::Type(EntryType::Text), content) => matches!(content, ClipboardContent::Text(_)),
+        (Filter::Type(EntryType::Kind(kind)), _) => entry.kind == *kind,
+        (Filter::Pinned, _) => entry.pinned,
+        (Filter::Primary, _) => entry.source == ClipboardSource::Primary,
+        (Filter::Collection(key), _) => entry
+            .collection
+            .as_deref()
+            .is_some_and(|name| collection_key(name) == *key),
+        (Filter::Tag(tag), _) => entry.tags.contains(tag),
+        (Filter::After(start), _) => entry.last_copied_at >= *start,
+        (Filter::Before(start), _) => entry.last_copied_at < *start,
+        (Filter::Length(comparison, bound), ClipboardContent::Text(text)) => {
+            comparison.holds(text.chars().count(), *bound)
+        }
+        (Filter::Length(..), _) => false,
+        (Filter::Regex(regex), ClipboardContent::Text(text)) => {
+            regex.is_match(text) || regex.is_match(&entry.title)
+        }
+        (Filter::Regex(regex), _) => regex.is_match(&entry.title),
+        (Filter::Contains(word), ClipboardContent::Text(text)) => {
+            text.to_lowercase().contains(word) || entry.title.to_lowercase().contains(word)
+        }
+        (Filter::Contains(word), _) => entry.title.to_lowercase().contains(word),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(input: &str) -> QueryError {
+        Query::parse(input).expect_err(input)
+    }
+
+    #[test]
+    fn words_with_colons_are_plain_text() {
+        for input in ["note: buy milk", "TODO:fix", "10:30", "https://example.com", "-note:done"] {
+            let query = Query::parse(input).expect(input);
+            assert!(query.terms.iter().all(|term| matches!(term.filter, Filter::Contains(_))), "{input}");
+        }
+        assert_eq!(Query::parse("note: buy milk").unwrap().text(), "note: buy milk");
+        assert_eq!(Query::parse("TODO:fix").unwrap().text(), "TODO:fix");
+    }
+
+    #[test]
+    fn plain_colon_words_match_text() {
+        let entry = ClipboardEntry::new("meeting".into(), ClipboardContent::Text("standup at 10:30".into()));
+        assert!(Query::parse("-10:45").unwrap().matches(&entry));
+        assert!(!Query::parse("-10:30").unwrap().matches(&entry));
+    }
+
+    #[test]
+    fn reserved_operators_report_malformed_values() {
+        assert_eq!(parse_err("type:video"), QueryError::UnknownType("video".into()));
+        assert_eq!(parse_err("is:starred"), QueryError::UnknownFlag("starred".into()));
+        assert_eq!(parse_err("in:"), QueryError::MissingValue("in".into()));
+        assert_eq!(parse_err("tag:#"), QueryError::InvalidTag("#".into()));
+        assert_eq!(parse_err("after:soon"), QueryError::InvalidDate("soon".into()));
+        assert_eq!(parse_err("before:2026-13-01"), QueryError::InvalidDate("2026-13-01".into()));
+        assert_eq!(parse_err("len:long"), QueryError::InvalidLength("long".into()));
+        assert_eq!(parse_err("/[a-/"), QueryError::InvalidRegex("[a-".into()));
+    }
+
+    #[test]
+    fn unclosed_slashes_are_plain_words() {
+        for input in ["/usr", "/usr/bin", "-/tmp", "/home/me/notes.txt", "/usr /tmp"] {
+            let query = Query::parse(input).expect(input);
+            assert!(query.terms.iter().all(|term| matches!(term.filter, Filter::Contains(_))), "{input}");
+        }
+        assert_eq!(Query::parse("/usr/bin").unwrap().text(), "/usr/bin");
+        assert_eq!(Query::parse("/usr lib").unwrap().text(), "/usr lib");
+        assert_eq!(Query::parse("/usr /tmp").unwrap().text(), "/usr /tmp");
+        assert!(matches!(Query::parse("/usr/bin/").unwrap().terms[0].filter, Filter::Regex(_)));
+    }
+
+    #[test]
+    fn reserved_operators_parse() {
+        for input in ["type:url", "is:pinned", "in:side-project", "tag:sql", "#sql", "after:today", "len:>5", "/a+/"] {
+            let query = Query::parse(input).expect(input);
+            assert_eq!(query.terms.len(), 1, "{input}");
+            assert!(query.text().is_empty(), "{input}");
+        }
+    }
+}
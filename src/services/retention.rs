@@ -0,0 +1,100 @@
+use std::time::{Duration, SystemTime};
+
+use crate::config::{Config, MIN_HISTORY};
+use crate::services::clipboard::{ClipboardContent, ClipboardEntry, ClipboardSource};
+
+/// How often time-based rules are checked while sensitive entries expire.
+const SENSITIVE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How often time-based rules are checked otherwise.
+const AGE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Rules deciding which unpinned entries stay in the history. Pinned entries are never dropped.
+///
+/// Every limit set to zero in the config is off, except the per-selection entry counts: no
+/// primary selection entries are kept then, and `max_history` is raised to [`MIN_HISTORY`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retention {
+    max_regular: usize,
+    max_primary: usize,
+    max_text: Option<usize>,
+    max_images: Option<usize>,
+    max_bytes: Option<usize>,
+    max_age: Option<Duration>,
+    sensitive_expiry: Option<Duration>,
+}
+
+impl Retention {
+    pub fn from_config(config: &Config) -> Self {
+        let enabled = |limit: usize| (limit > 0).then_some(limit);
+        let after_secs = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+
+        Retention {
+            max_regular: config.max_history.max(MIN_HISTORY),
+            max_primary: config.max_primary_history,
+            max_text: enabled(config.max_text_entries),
+            max_images: enabled(config.max_image_entries),
+            max_bytes: enabled(config.max_history_bytes),
+            max_age: after_secs(config.max_age_hours.saturating_mul(60 * 60)),
+            sensitive_expiry: after_secs(config.sensitive_expiry_secs),
+        }
+    }
+
+    /// How often the history has to be checked for expired entries, `None` without time-based rules.
+    pub fn check_interval(&self) -> Option<Duration> {
+        if self.sensitive_expiry.is_some() {
+            Some(SENSITIVE_CHECK_INTERVAL)
+        } else {
+            self.max_age.map(|_| AGE_CHECK_INTERVAL)
+        }
+    }
+
+    /// Predicate for the unpinned entries, fed newest first, that keeps the newest entries
+    /// within every limit. An entry larger than what is left of the byte budget is dropped even
+    /// if older, smaller ones still fit.
+    pub fn keeper(&self, now: SystemTime) -> impl FnMut(&ClipboardEntry) -> bool + '_ {
+        let (mut regular, mut primary, mut text, mut images, mut bytes) = (0, 0, 0, 0, 0);
+
+        move |entry| {
+            let age = now.duration_since(entry.last_copied_at).unwrap_or_default();
+            if self.max_age.is_some_and(|max_age| age > max_age)
+                || (entry.sensitive && self.sensitive_expiry.is_some_and(|expiry| age > expiry))
+            {
+                return false;
+            }
+
+            let (source_count, source_max) = match entry.source {
+                ClipboardSource::Regular => (&mut regular, self.max_regular),
+                ClipboardSource::Primary => (&mut primary, self.max_primary),
+            };
+            let (kind_count, kind_max) = match entry.content {
+                ClipboardContent::Text(_) => (&mut text, self.max_text),
+                ClipboardContent::Image { .. } => (&mut images, self.max_images),
+            };
+            let size = entry_size(entry);
+
+            let fits = *source_count < source_max
+                && kind_max.is_none_or(|max| *kind_count < max)
+                && self.max_bytes.is_none_or(|max| bytes + size <= max);
+            if fits {
+                *source_count += 1;
+                *kind_count += 1;
+                bytes += size;
+            }
+            fits
+        }
+    }
+}
+
+/// Bytes an entry takes up: its content plus every extra format it offers.
+fn entry_size(entry: &ClipboardEntry) -> usize {
+    let content = match &entry.content {
+        ClipboardContent::Text(text) => text.len(),
+        ClipboardContent::Image { bytes, .. } => bytes.len(),
+    };
+    content
+        + entry
+            .representations
+            .iter()
+            .map(|representation| representation.bytes.len())
+            .sum::<usize>()
+}
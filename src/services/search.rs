@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use crate::services::clipboard::{ClipboardContent, ClipboardEntry};
+use crate::services::query::Query;
+
+const SCORE_MATCH: i64 = 1;
+const BONUS_CONSECUTIVE: i64 = 5;
+const BONUS_WORD_START: i64 = 3;
+const BONUS_CONTIGUOUS: i64 = 10;
+/// Title hits rank above hits that are only in the content.
+const BONUS_TITLE: i64 = 15;
+const MAX_GAP_PENALTY: i64 = 3;
+/// Only the start of longer text entries is fuzzy matched, so huge entries don't slow the search.
+const MAX_MATCHED_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Byte ranges of the matched characters, sorted and non-overlapping.
+    pub ranges: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub score: i64,
+    /// Byte ranges in the entry title to highlight.
+    pub title_ranges: Vec<Range<usize>>,
+}
+
+struct Char {
+    byte: usize,
+    len: usize,
+    lower: char,
+    word_start: bool,
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn chars(haystack: &str) -> Vec<Char> {
+    let mut prev: Option<char> = None;
+    haystack
+        .char_indices()
+        .map(|(byte, c)| {
+            let word_start = prev.is_none_or(|prev| !prev.is_alphanumeric());
+            prev = Some(c);
+            Char {
+                byte,
+                len: c.len_utf8(),
+                lower: lower(c),
+                word_start,
+            }
+        })
+        .collect()
+}
+
+/// Case-insensitive subsequence match of every whitespace separated word in `query`.
+///
+/// A contiguous occurrence of a word is preferred over a scattered one.
+pub fn fuzzy_match(query: &str, haystack: &str) -> Option<FuzzyMatch> {
+    let hay = chars(haystack);
+    let mut result = FuzzyMatch::default();
+
+    for word in query.split_whitespace() {
+        let needle: Vec<char> = word.chars().map(lower).collect();
+        let (score, positions) = match_contiguous(&hay, &needle)
+            .or_else(|| match_subsequence(&hay, &needle))?;
+
+        result.score += score;
+        result
+            .ranges
+            .extend(positions.into_iter().map(|i| hay[i].byte..hay[i].byte + hay[i].len));
+    }
+
+    result.ranges = merge_ranges(result.ranges);
+    Some(result)
+}
+
+fn match_contiguous(hay: &[Char], needle: &[char]) -> Option<(i64, Vec<usize>)> {
+    if needle.is_empty() || needle.len() > hay.len() {
+        return None;
+    }
+
+    let start = (0..=hay.len() - needle.len()).find(|&start| {
+        needle
+            .iter()
+            .enumerate()
+            .all(|(offset, c)| hay[start + offset].lower == *c)
+    })?;
+
+    let len = needle.len() as i64;
+    let mut score = len * SCORE_MATCH + (len - 1) * BONUS_CONSECUTIVE + BONUS_CONTIGUOUS;
+    if hay[start].word_start {
+        score += BONUS_WORD_START;
+    }
+
+    Some((score, (start..start + needle.len()).collect()))
+}
+
+fn match_subsequence(hay: &[Char], needle: &[char]) -> Option<(i64, Vec<usize>)> {
+    let mut positions = Vec::with_capacity(needle.len());
+    let mut score = 0;
+    let mut next = needle.iter().peekable();
+
+    for (index, c) in hay.iter().enumerate() {
+        let Some(wanted) = next.peek() else {
+            break;
+        };
+        if c.lower != **wanted {
+            continue;
+        }
+
+        score += SCORE_MATCH;
+        if c.word_start {
+            score += BONUS_WORD_START;
+        }
+        match positions.last() {
+            Some(&prev) if prev + 1 == index => score += BONUS_CONSECUTIVE,
+            Some(&prev) => score -= ((index - prev - 1) as i64).min(MAX_GAP_PENALTY),
+            None => {}
+        }
+
+        positions.push(index);
+        next.next();
+    }
+
+    next.peek().is_none().then_some((score, positions))
+}
+
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Matches `query` against the entry title, the start of its text content and image metadata.
+///
+/// Sensitive entries never match a non-empty query.
+pub fn match_entry(query: &str, entry: &ClipboardEntry) -> Option<SearchMatch> {
+    if query.trim().is_empty() {
+        return Some(SearchMatch::default());
+    }
+    if entry.sensitive {
+        return None;
+    }
+
+    let title = fuzzy_match(query, &entry.title);
+    let content = match &entry.content {
+        ClipboardContent::Text(text) => fuzzy_match(query, matched_prefix(text)),
+        ClipboardContent::Image { mime, .. } => fuzzy_match(query, &format!("image {mime}")),
+    };
+
+    match (title, content) {
+        (Some(title), content) => Some(SearchMatch {
+            score: (title.score + BONUS_TITLE).max(content.map_or(0, |content| content.score)),
+            title_ranges: title.ranges,
+        }),
+        (None, Some(content)) => Some(SearchMatch {
+            score: content.score,
+            title_ranges: Vec::new(),
+        }),
+        (None, None) => None,
+    }
+}
+
+/// The first [`MAX_MATCHED_BYTES`] of `text`, cut at a character boundary.
+fn matched_prefix(text: &str) -> &str {
+    if text.len() <= MAX_MATCHED_BYTES {
+        return text;
+    }
+    let end = (0..=MAX_MATCHED_BYTES).rev().find(|&end| text.is_char_boundary(end)).unwrap_or(0);
+    &text[..end]
+}
+
+/// Filters `entries` by the query operators, then ranks the free text, keeping history order for ties.
+pub fn search<'a>(
+    query: &Query,
+    entries: impl Iterator<Item = &'a ClipboardEntry>,
+) -> Vec<(&'a ClipboardEntry, SearchMatch)> {
+    let mut results: Vec<_> = entries
+        .filter(|entry| query.matches(entry))
+        .filter_map(|entry| match_entry(query.text(), entry).map(|found| (entry, found)))
+        .collect();
+
+    results.sort_by(|(_, a), (_, b)| b.score.cmp(&a.score));
+    results
+}
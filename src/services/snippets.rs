@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+
+/// A named text template loaded from the snippets directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub content: String,
+}
+
+/// A `.toml` snippet file holds either one snippet or a list of `[[snippet]]` tables.
+#[derive(Debug, Deserialize)]
+struct SnippetFile {
+    name: Option<String>,
+    content: Option<String>,
+    #[serde(default, rename = "snippet")]
+    snippets: Vec<TomlSnippet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TomlSnippet {
+    name: String,
+    content: String,
+}
+
+/// `$XDG_CONFIG_HOME/clippy-land/snippets`
+pub fn snippets_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("clippy-land").join("snippets"))
+}
+
+/// Loads every `*.txt` and `*.toml` snippet in `dir`, sorted by name.
+pub fn load_snippets(dir: &Path) -> Vec<Snippet> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut snippets = Vec::new();
+    for path in read_dir.flatten().map(|entry| entry.path()) {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("txt") => snippets.extend(load_text_snippet(&path)),
+            Some("toml") => snippets.extend(load_toml_snippets(&path)),
+            _ => {}
+        }
+    }
+
+    snippets.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    snippets
+}
+
+fn load_text_snippet(path: &Path) -> Option<Snippet> {
+    let name = path.file_stem()?.to_string_lossy().into_owned();
+    let content = fs::read_to_string(path)
+        .map_err(|err| eprintln!("[clippy-land] failed to read snippet {}: {err}", path.display()))
+        .ok()?;
+
+    Some(Snippet {
+        name,
+        content: content.trim_end_matches(['\n', '\r']).to_string(),
+    })
+}
+
+fn load_toml_snippets(path: &Path) -> Vec<Snippet> {
+    let file = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|raw| toml::from_str::<SnippetFile>(&raw).map_err(|err| err.to_string()));
+
+    let file = match file {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("[clippy-land] failed to load snippets from {}: {err}", path.display());
+            return Vec::new();
+        }
+    };
+
+    let mut snippets: Vec<Snippet> = file
+        .snippets
+        .into_iter()
+        .map(|snippet| Snippet {
+            name: snippet.name,
+            content: snippet.content,
+        })
+        .collect();
+
+    if let Some(content) = file.content {
+        let name = file.name.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        snippets.push(Snippet { name, content });
+    }
+
+    snippets
+}
+
+/// Calls `on_change` whenever something inside `dir` changes. The watcher stops when dropped.
+pub fn watch_snippets(dir: &Path, on_change: impl Fn() + Send + 'static) -> notify::Result<RecommendedWatcher> {
+    fs::create_dir_all(dir).map_err(notify::Error::io)?;
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok() {
+            on_change();
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
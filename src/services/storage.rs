@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::services::clipboard::{self, ClipboardContent, ClipboardEntry, ClipboardSource, Representation};
+
+/// Bump whenever the layout of [`HistoryFile`] changes and add a migration in [`HistoryStore::load`].
+const STORE_VERSION: u32 = 1;
+const STORE_DIR_NAME: &str = "clippy-land";
+const HISTORY_FILE_NAME: &str = "history.json";
+const BLOBS_DIR_NAME: &str = "blobs";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    entries: Vec<StoredEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredEntry {
+    /// Histories written before entries had ids get fresh ones.
+    #[serde(default = "Uuid::new_v4")]
+    id: Uuid,
+    title: String,
+    pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    collection: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default)]
+    source: ClipboardSource,
+    /// Seconds since the unix epoch.
+    created_at: u64,
+    /// Seconds since the unix epoch.
+    last_copied_at: u64,
+    content: StoredContent,
+    #[serde(default)]
+    representations: Vec<StoredRepresentation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredRepresentation {
+    mime: String,
+    blob: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StoredContent {
+    Text { text: String },
+    /// Image bytes live in `blobs/<blob>` next to the history file.
+    Image { mime: String, blob: String },
+}
+
+/// On-disk clipboard history, stored under `$XDG_DATA_HOME/clippy-land`.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    root: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn open_default() -> Option<Self> {
+        dirs::data_dir().map(|dir| Self::open(dir.join(STORE_DIR_NAME)))
+    }
+
+    pub fn open(root: PathBuf) -> Self {
+        HistoryStore { root }
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.root.join(HISTORY_FILE_NAME)
+    }
+
+    fn blobs_dir(&self) -> PathBuf {
+        self.root.join(BLOBS_DIR_NAME)
+    }
+
+    /// Loads every persisted entry, pinned and unpinned, in their stored order.
+    pub fn load(&self, thumbnail_size_px: u32) -> Vec<ClipboardEntry> {
+        let raw = match fs::read(self.history_path()) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Vec::new(),
+            Err(err) => {
+                eprintln!("[clippy-land] failed to read clipboard history: {err}");
+                return Vec::new();
+            }
+        };
+
+        let file: HistoryFile = match serde_json::from_slice(&raw) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("[clippy-land] failed to parse clipboard history: {err}");
+                return Vec::new();
+            }
+        };
+
+        if file.version > STORE_VERSION {
+            eprintln!(
+                "[clippy-land] clipboard history version {} is newer than supported version {}",
+                file.version, STORE_VERSION
+            );
+            return Vec::new();
+        }
+
+        file.entries
+            .into_iter()
+            .filter_map(|stored| self.restore_entry(stored, thumbnail_size_px))
+            .collect()
+    }
+
+    /// Writes the index and any image blobs that are not on disk yet, then drops unreferenced blobs.
+    ///
+    /// Sensitive entries are never written.
+    pub fn save<'a>(&self, entries: impl IntoIterator<Item = &'a ClipboardEntry>) -> io::Result<()> {
+        // Entries can hold passwords copied before they were marked sensitive, so keep the store
+        // private to the user, also when an older version created it with the default umask.
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(self.blobs_dir())?;
+        fs::set_permissions(&self.root, fs::Permissions::from_mode(0o700))?;
+
+        let mut referenced_blobs = HashSet::new();
+        let mut stored_entries = Vec::new();
+        for entry in entries.into_iter().filter(|entry| !entry.sensitive) {
+            let content = match &entry.content {
+                ClipboardContent::Text(text) => StoredContent::Text { text: text.clone() },
+                ClipboardContent::Image { mime, bytes, hash, .. } => {
+                    let blob = self.write_blob(*hash, bytes)?;
+                    referenced_blobs.insert(blob.clone());
+                    StoredContent::Image { mime: mime.clone(), blob }
+                }
+            };
+
+            let mut representations = Vec::new();
+            for representation in &entry.representations {
+                let blob = self.write_blob(representation.hash, &representation.bytes)?;
+                referenced_blobs.insert(blob.clone());
+                representations.push(StoredRepresentation {
+                    mime: representation.mime.clone(),
+                    blob,
+                });
+            }
+
+            stored_entries.push(StoredEntry {
+                id: entry.id,
+                title: entry.title.clone(),
+                pinned: entry.pinned,
+                collection: entry.collection.clone(),
+                tags: entry.tags.clone(),
+                source: entry.source,
+                created_at: to_unix_secs(entry.created_at),
+                last_copied_at: to_unix_secs(entry.last_copied_at),
+                content,
+                representations,
+            });
+        }
+
+        let file = HistoryFile {
+            version: STORE_VERSION,
+            entries: stored_entries,
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(io::Error::other)?;
+        write_atomic(&self.history_path(), &json)?;
+
+        for blob in fs::read_dir(self.blobs_dir())?.flatten() {
+            let name = blob.file_name();
+            if !referenced_blobs.contains(name.to_string_lossy().as_ref()) {
+                _ = fs::remove_file(blob.path());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes `bytes` under a name derived from `hash`, skipping blobs that already exist.
+    fn write_blob(&self, hash: u64, bytes: &[u8]) -> io::Result<String> {
+        let blob = format!("{hash:016x}");
+        let blob_path = self.blobs_dir().join(&blob);
+        if !blob_path.exists() {
+            write_atomic(&blob_path, bytes)?;
+        }
+        Ok(blob)
+    }
+
+    fn restore_entry(&self, stored: StoredEntry, thumbnail_size_px: u32) -> Option<ClipboardEntry> {
+        let content = match stored.content {
+            StoredContent::Text { text } => ClipboardContent::Text(text),
+            StoredContent::Image { mime, blob } => match fs::read(self.blobs_dir().join(&blob)) {
+                Ok(bytes) => ClipboardContent::image(mime, bytes, thumbnail_size_px),
+                Err(err) => {
+                    eprintln!("[clippy-land] dropping image entry with missing blob {blob}: {err}");
+                    return None;
+                }
+            },
+        };
+
+        let mut entry = ClipboardEntry::new(stored.title, content);
+        entry.representations = stored
+            .representations
+            .into_iter()
+            .filter_map(|representation| {
+                // A missing extra format only loses that format, not the whole entry.
+                let bytes = fs::read(self.blobs_dir().join(&representation.blob)).ok()?;
+                Some(Representation::new(representation.mime, bytes))
+            })
+            .collect();
+        entry.kind = clipboard::classify(&entry.content, &entry.representations);
+        entry.id = stored.id;
+        entry.pinned = stored.pinned;
+        entry.collection = stored.collection;
+        entry.tags = stored.tags;
+        entry.source = stored.source;
+        entry.created_at = from_unix_secs(stored.created_at);
+        entry.last_copied_at = from_unix_secs(stored.last_copied_at);
+        Some(entry)
+    }
+}
+
+/// Quiet time after the last snapshot before it is written, so bursts of copies or drag
+/// reorders end up as one save.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Saves history snapshots to a [`HistoryStore`] on a background thread, so disk I/O never
+/// blocks the caller. A snapshot is written once no newer one arrived for [`SAVE_DELAY`], and
+/// dropping the writer waits until the last one is on disk.
+#[derive(Debug)]
+pub struct HistoryWriter {
+    snapshots: Option<mpsc::Sender<Vec<ClipboardEntry>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HistoryWriter {
+    pub fn spawn(store: HistoryStore) -> Self {
+        let (snapshots, queue) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let save = |entries: Option<Vec<ClipboardEntry>>| {
+                if let Some(Err(err)) = entries.map(|entries| store.save(&entries)) {
+                    eprintln!("[clippy-land] failed to save clipboard history: {err}");
+                }
+            };
+
+            let mut pending = None;
+            loop {
+                let next = match pending {
+                    Some(_) => queue.recv_timeout(SAVE_DELAY),
+                    None => queue.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match next {
+                    Ok(entries) => pending = Some(entries),
+                    Err(RecvTimeoutError::Timeout) => save(pending.take()),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        save(pending.take());
+                        break;
+                    }
+                }
+            }
+        });
+
+        HistoryWriter {
+            snapshots: Some(snapshots),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn save(&self, entries: Vec<ClipboardEntry>) {
+        if let Some(snapshots) = &self.snapshots {
+            _ = snapshots.send(entries);
+        }
+    }
+}
+
+impl Drop for HistoryWriter {
+    /// Writes the snapshot still waiting out its delay, so changes made right before exit are kept.
+    fn drop(&mut self) {
+        self.snapshots = None;
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+/// Writes through a temporary file readable only by the user, then moves it into place.
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    // A leftover temporary file would keep its old permissions.
+    _ = fs::remove_file(&tmp);
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&tmp)?;
+    file.write_all(bytes)?;
+    fs::rename(&tmp, path)
+}
+
+fn to_unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn from_unix_secs(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
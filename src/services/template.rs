@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use chrono::format::{Item, StrftimeItems};
+
+use crate::services::backend::ClipboardBackend;
+use crate::services::clipboard;
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder {
+    /// `{date}` or `{date:<strftime format>}`
+    Date(String),
+    /// `{clipboard}`, the current clipboard text
+    Clipboard,
+    /// `{env:NAME}`
+    Env(String),
+    /// `{uuid}`, a random v4 UUID
+    Uuid,
+    /// `{prompt:Label}`, asked for in the popup before copying
+    Prompt(String),
+}
+
+/// Text with `{…}` placeholders that are expanded at copy time.
+///
+/// Escaping works like Rust's `format!`: `{{` and `}}` produce a literal `{` and `}`, also when
+/// unbalanced, so `{{ {{x}} }}` renders as `{ {x} }`. Placeholders do not nest: one runs from the
+/// last `{` before the first `}`, so in `{a{uuid}}` only `{uuid}` is expanded. Unknown
+/// placeholders, a lone `}` and a `{` that is never closed are kept as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find(['{', '}']) {
+            literal.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                literal.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+
+            let end = match rest.find('}') {
+                Some(0) => {
+                    literal.push('}');
+                    rest = &rest[1..];
+                    continue;
+                }
+                Some(end) => end,
+                None => break,
+            };
+            // Another `{` before the closing brace opens the placeholder instead.
+            if rest[1..end].contains('{') {
+                literal.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+
+            match parse_placeholder(&rest[1..end]) {
+                Some(placeholder) => {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                None => literal.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Template { segments }
+    }
+
+    pub fn has_placeholders(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Placeholder(_)))
+    }
+
+    /// Labels of the `{prompt:…}` placeholders, in order of first appearance.
+    pub fn prompts(&self) -> Vec<String> {
+        let mut prompts: Vec<String> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Placeholder(Placeholder::Prompt(label)) = segment {
+                if !prompts.contains(label) {
+                    prompts.push(label.clone());
+                }
+            }
+        }
+        prompts
+    }
+
+    /// Expands every placeholder; prompts without an answer expand to an empty string.
+    /// `{clipboard}` is read from `backend`.
+    pub fn render(&self, answers: &HashMap<String, String>, backend: &dyn ClipboardBackend) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Placeholder(Placeholder::Date(format)) => {
+                    out.push_str(&chrono::Local::now().format(format).to_string());
+                }
+                Segment::Placeholder(Placeholder::Clipboard) => {
+                    out.push_str(&clipboard::read_clipboard_text(backend, clipboard::ClipboardSource::Regular).unwrap_or_default());
+                }
+                Segment::Placeholder(Placeholder::Env(name)) => {
+                    out.push_str(&std::env::var(name).unwrap_or_default());
+                }
+                Segment::Placeholder(Placeholder::Uuid) => {
+                    out.push_str(&uuid::Uuid::new_v4().to_string());
+                }
+                Segment::Placeholder(Placeholder::Prompt(label)) => {
+                    out.push_str(answers.get(label).map(String::as_str).unwrap_or_default());
+                }
+            }
+        }
+        out
+    }
+}
+
+fn parse_placeholder(inner: &str) -> Option<Placeholder> {
+    let (name, arg) = match inner.split_once(':') {
+        Some((name, arg)) => (name.trim(), Some(arg)),
+        None => (inner.trim(), None),
+    };
+
+    match (name, arg) {
+        ("date", None) => Some(Placeholder::Date(DEFAULT_DATE_FORMAT.to_string())),
+        // An invalid strftime format would panic while rendering, so leave it as text.
+        ("date", Some(format)) if !StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) => {
+            Some(Placeholder::Date(format.to_string()))
+        }
+        ("clipboard", None) => Some(Placeholder::Clipboard),
+        ("env", Some(var)) if !var.trim().is_empty() => Some(Placeholder::Env(var.trim().to_string())),
+        ("uuid", None) => Some(Placeholder::Uuid),
+        ("prompt", Some(label)) if !label.trim().is_empty() => {
+            Some(Placeholder::Prompt(label.trim().to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// A template waiting for the user to answer its `{prompt:…}` placeholders one at a time.
+#[derive(Debug, Clone)]
+pub struct PromptSession {
+    template: Template,
+    prompts: Vec<String>,
+    answers: HashMap<String, String>,
+    pub input: String,
+}
+
+impl PromptSession {
+    /// Returns `None` when the template has no prompts and can be rendered right away.
+    pub fn new(template: Template) -> Option<Self> {
+        let mut prompts = template.prompts();
+        if prompts.is_empty() {
+            return None;
+        }
+        prompts.reverse();
+
+        Some(PromptSession {
+            template,
+            prompts,
+            answers: HashMap::new(),
+            input: String::new(),
+        })
+    }
+
+    pub fn current_prompt(&self) -> Option<&str> {
+        self.prompts.last().map(String::as_str)
+    }
+
+    /// Records the current input as the answer and returns the rendered text once every
+    /// prompt has been answered.
+    pub fn submit(&mut self, backend: &dyn ClipboardBackend) -> Option<String> {
+        let label = self.prompts.pop()?;
+        self.answers.insert(label, std::mem::take(&mut self.input));
+
+        self.prompts
+            .is_empty()
+            .then(|| self.template.render(&self.answers, backend))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::backend::MemoryClipboard;
+
+    fn render(text: &str) -> String {
+        Template::parse(text).render(&HashMap::new(), &MemoryClipboard::new())
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{uuid}}"), "{uuid}");
+        assert!(!Template::parse("{{uuid}}").has_placeholders());
+        assert_eq!(render("a }} b"), "a } b");
+        assert_eq!(render("{{ {{x}} }}"), "{ {x} }");
+    }
+
+    #[test]
+    fn unbalanced_braces_are_kept() {
+        assert_eq!(render("a } b"), "a } b");
+        assert_eq!(render("{{date"), "{date");
+        assert_eq!(render("{{"), "{");
+        assert_eq!(render("{date"), "{date");
+        assert_eq!(render("{"), "{");
+    }
+
+    #[test]
+    fn placeholders_do_not_nest() {
+        let rendered = render("{a{uuid}}");
+        assert!(rendered.starts_with("{a") && rendered.ends_with('}'), "{rendered}");
+        assert_eq!(rendered.len(), "{a}".len() + 36);
+    }
+
+    #[test]
+    fn text_that_only_looks_like_placeholders_is_unchanged() {
+        for text in [r#"{"a":{"b":1}}"#, "{unknown}", "{env:}", "fn main() { }"] {
+            assert_eq!(render(text), text);
+            assert!(!Template::parse(text).has_placeholders(), "{text}");
+        }
+    }
+
+    #[test]
+    fn prompts_are_collected_once() {
+        let template = Template::parse("{prompt:Name} and {prompt: Name } {{prompt:Escaped}}");
+        assert_eq!(template.prompts(), ["Name"]);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use crate::fl;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransformError {
+    InvalidUrlEncoding,
+    InvalidBase64,
+    /// Decoded bytes are not valid UTF-8 text.
+    NotText,
+    InvalidJson(String),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            TransformError::InvalidUrlEncoding => fl!("transform-invalid-url-encoding"),
+            TransformError::InvalidBase64 => fl!("transform-invalid-base64"),
+            TransformError::NotText => fl!("transform-not-text"),
+            TransformError::InvalidJson(reason) => fl!("transform-invalid-json", reason = reason.as_str()),
+        };
+        f.write_str(&message)
+    }
+}
+
+/// A single text transformation offered in the "Copy As…" menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    Trim,
+    /// Joins all lines into one, separated by single spaces.
+    CollapseLines,
+    Uppercase,
+    Lowercase,
+    TitleCase,
+    /// Drops control and zero-width characters and replaces typographic punctuation with ASCII.
+    StripFormatting,
+    UrlEncode,
+    UrlDecode,
+    Base64Encode,
+    Base64Decode,
+    JsonPretty,
+    JsonMinify,
+    SortLines,
+    DedupeLines,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 14] = [
+        Transform::Trim,
+        Transform::CollapseLines,
+        Transform::Uppercase,
+        Transform::Lowercase,
+        Transform::TitleCase,
+        Transform::StripFormatting,
+        Transform::UrlEncode,
+        Transform::UrlDecode,
+        Transform::Base64Encode,
+        Transform::Base64Decode,
+        Transform::JsonPretty,
+        Transform::JsonMinify,
+        Transform::SortLines,
+        Transform::DedupeLines,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            Transform::Trim => fl!("transform-trim"),
+            Transform::CollapseLines => fl!("transform-collapse-lines"),
+            Transform::Uppercase => fl!("transform-uppercase"),
+            Transform::Lowercase => fl!("transform-lowercase"),
+            Transform::TitleCase => fl!("transform-title-case"),
+            Transform::StripFormatting => fl!("transform-strip-formatting"),
+            Transform::UrlEncode => fl!("transform-url-encode"),
+            Transform::UrlDecode => fl!("transform-url-decode"),
+            Transform::Base64Encode => fl!("transform-base64-encode"),
+            Transform::Base64Decode => fl!("transform-base64-decode"),
+            Transform::JsonPretty => fl!("transform-json-pretty"),
+            Transform::JsonMinify => fl!("transform-json-minify"),
+            Transform::SortLines => fl!("transform-sort-lines"),
+            Transform::DedupeLines => fl!("transform-dedupe-lines"),
+        }
+    }
+
+    pub fn apply(self, text: &str) -> Result<String, TransformError> {
+        let output = match self {
+            Transform::Trim => text.trim().to_string(),
+            Transform::CollapseLines => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            Transform::Uppercase => text.to_uppercase(),
+            Transform::Lowercase => text.to_lowercase(),
+            Transform::TitleCase => title_case(text),
+            Transform::StripFormatting => strip_formatting(text),
+            Transform::UrlEncode => urlencoding::encode(text).into_owned(),
+            Transform::UrlDecode => urlencoding::decode(text.trim())
+                .map_err(|_| TransformError::InvalidUrlEncoding)?
+                .into_owned(),
+            Transform::Base64Encode => BASE64.encode(text),
+            Transform::Base64Decode => {
+                let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+                let bytes = BASE64
+                    .decode(compact)
+                    .map_err(|_| TransformError::InvalidBase64)?;
+                String::from_utf8(bytes).map_err(|_| TransformError::NotText)?
+            }
+            Transform::JsonPretty => serde_json::to_string_pretty(&parse_json(text)?)
+                .map_err(|err| TransformError::InvalidJson(err.to_string()))?,
+            Transform::JsonMinify => serde_json::to_string(&parse_json(text)?)
+                .map_err(|err| TransformError::InvalidJson(err.to_string()))?,
+            Transform::SortLines => {
+                let mut lines: Vec<&str> = text.lines().collect();
+                lines.sort_unstable();
+                lines.join("\n")
+            }
+            Transform::DedupeLines => {
+                let mut seen = HashSet::new();
+                text.lines()
+                    .filter(|line| seen.insert(*line))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        };
+        Ok(output)
+    }
+}
+
+/// Applies `transforms` in order, stopping at the first failure.
+pub fn apply_pipeline(transforms: &[Transform], text: &str) -> Result<String, TransformError> {
+    transforms
+        .iter()
+        .try_fold(text.to_string(), |current, transform| transform.apply(&current))
+}
+
+fn parse_json(text: &str) -> Result<serde_json::Value, TransformError> {
+    serde_json::from_str(text.trim()).map_err(|err| TransformError::InvalidJson(err.to_string()))
+}
+
+fn title_case(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if word_start {
+            output.extend(c.to_uppercase());
+        } else {
+            output.extend(c.to_lowercase());
+        }
+        word_start = !c.is_alphanumeric() && c != '\'';
+    }
+    output
+}
+
+fn strip_formatting(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => Some('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => Some('"'),
+            '\u{2013}' | '\u{2014}' | '\u{2212}' => Some('-'),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => Some(' '),
+            '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' => None,
+            '\n' | '\t' => Some(c),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_base64_is_an_error() {
+        assert_eq!(Transform::Base64Decode.apply("not base64!"), Err(TransformError::InvalidBase64));
+        assert_eq!(Transform::Base64Decode.apply(&BASE64.encode([0xff, 0xfe])), Err(TransformError::NotText));
+        assert_eq!(Transform::Base64Decode.apply("aGVs\nbG8=").as_deref(), Ok("hello"));
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        for transform in [Transform::JsonPretty, Transform::JsonMinify] {
+            for text in ["{\"a\": }", "", "[1, 2"] {
+                assert!(matches!(transform.apply(text), Err(TransformError::InvalidJson(_))), "{transform:?} {text:?}");
+            }
+        }
+        assert_eq!(Transform::JsonMinify.apply(" { \"a\": [1, 2] }\n").as_deref(), Ok("{\"a\":[1,2]}"));
+        assert_eq!(Transform::JsonPretty.apply("{\"a\":1}").as_deref(), Ok("{\n  \"a\": 1\n}"));
+    }
+
+    #[test]
+    fn title_case_starts_words_after_punctuation() {
+        assert_eq!(
+            Transform::TitleCase.apply("hello, WORLD-wide (web) don't stop").as_deref(),
+            Ok("Hello, World-Wide (Web) Don't Stop")
+        );
+    }
+
+    #[test]
+    fn dedupe_keeps_the_first_occurrence_in_order() {
+        assert_eq!(Transform::DedupeLines.apply("b\na\nb\nc\na").as_deref(), Ok("b\na\nc"));
+    }
+
+    #[test]
+    fn url_encoding_round_trips() {
+        let text = "a b&c=d/é?";
+        let encoded = Transform::UrlEncode.apply(text).unwrap();
+        assert_eq!(encoded, "a%20b%26c%3Dd%2F%C3%A9%3F");
+        assert_eq!(Transform::UrlDecode.apply(&encoded).as_deref(), Ok(text));
+        assert_eq!(Transform::UrlDecode.apply("%FF"), Err(TransformError::InvalidUrlEncoding));
+    }
+}
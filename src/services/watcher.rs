@@ -0,0 +1,239 @@
+use std::fmt;
+
+use crate::services::clipboard::ClipboardSource;
+
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, event_created_child,
+    globals::{GlobalListContents, registry_queue_init},
+    protocol::{wl_registry, wl_seat::WlSeat},
+};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1::{self, ExtDataControlDeviceV1},
+    ext_data_control_manager_v1::ExtDataControlManagerV1,
+    ext_data_control_offer_v1::ExtDataControlOfferV1,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::ZwlrDataControlOfferV1,
+};
+
+#[derive(Debug)]
+pub enum WatchError {
+    /// The compositor offers neither `ext-data-control-v1` nor `zwlr-data-control-v1`.
+    Unsupported,
+    Connection(String),
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchError::Unsupported => write!(f, "compositor does not support data-control"),
+            WatchError::Connection(err) => write!(f, "wayland connection error: {err}"),
+        }
+    }
+}
+
+enum Offer {
+    Ext(ExtDataControlOfferV1),
+    Wlr(ZwlrDataControlOfferV1),
+}
+
+impl Offer {
+    fn destroy(self) {
+        match self {
+            Offer::Ext(offer) => offer.destroy(),
+            Offer::Wlr(offer) => offer.destroy(),
+        }
+    }
+}
+
+struct WatchState {
+    on_change: Box<dyn FnMut(ClipboardSource) -> bool + Send>,
+    selection: Option<Offer>,
+    primary_selection: Option<Offer>,
+    running: bool,
+}
+
+impl WatchState {
+    fn selection_changed(&mut self, offer: Option<Offer>) {
+        if let Some(old) = std::mem::replace(&mut self.selection, offer) {
+            old.destroy();
+        }
+        if !(self.on_change)(ClipboardSource::Regular) {
+            self.running = false;
+        }
+    }
+
+    fn primary_selection_changed(&mut self, offer: Option<Offer>) {
+        if let Some(old) = std::mem::replace(&mut self.primary_selection, offer) {
+            old.destroy();
+        }
+        if !(self.on_change)(ClipboardSource::Primary) {
+            self.running = false;
+        }
+    }
+}
+
+/// Blocks the calling thread and calls `on_change` every time the compositor announces a new
+/// regular or primary selection, including once for the current ones right after binding.
+///
+/// Returns when `on_change` returns `false` or the data-control device is finished.
+pub fn watch_selection(
+    on_change: impl FnMut(ClipboardSource) -> bool + Send + 'static,
+) -> Result<(), WatchError> {
+    let conn = Connection::connect_to_env().map_err(|err| WatchError::Connection(err.to_string()))?;
+    let (globals, mut queue) = registry_queue_init::<WatchState>(&conn)
+        .map_err(|err| WatchError::Connection(err.to_string()))?;
+    let qh = queue.handle();
+
+    let seat: WlSeat = globals.bind(&qh, 1..=7, ()).map_err(|_| WatchError::Unsupported)?;
+
+    // Prefer the standardized protocol and fall back to the wlroots one.
+    if let Ok(manager) = globals.bind::<ExtDataControlManagerV1, _, _>(&qh, 1..=1, ()) {
+        manager.get_data_device(&seat, &qh, ());
+    } else if let Ok(manager) = globals.bind::<ZwlrDataControlManagerV1, _, _>(&qh, 1..=2, ()) {
+        manager.get_data_device(&seat, &qh, ());
+    } else {
+        return Err(WatchError::Unsupported);
+    }
+
+    let mut state = WatchState {
+        on_change: Box::new(on_change),
+        selection: None,
+        primary_selection: None,
+        running: true,
+    };
+
+    while state.running {
+        queue
+            .blocking_dispatch(&mut state)
+            .map_err(|err| WatchError::Connection(err.to_string()))?;
+    }
+
+    Ok(())
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for WatchState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for WatchState {
+    fn event(
+        _: &mut Self,
+        _: &WlSeat,
+        _: <WlSeat as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtDataControlManagerV1, ()> for WatchState {
+    fn event(
+        _: &mut Self,
+        _: &ExtDataControlManagerV1,
+        _: <ExtDataControlManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for WatchState {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlManagerV1,
+        _: <ZwlrDataControlManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtDataControlDeviceV1, ()> for WatchState {
+    fn event(
+        state: &mut Self,
+        _: &ExtDataControlDeviceV1,
+        event: ext_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_data_control_device_v1::Event::Selection { id } => {
+                state.selection_changed(id.map(Offer::Ext));
+            }
+            ext_data_control_device_v1::Event::PrimarySelection { id } => {
+                state.primary_selection_changed(id.map(Offer::Ext));
+            }
+            ext_data_control_device_v1::Event::Finished => state.running = false,
+            _ => {}
+        }
+    }
+
+    event_created_child!(WatchState, ExtDataControlDeviceV1, [
+        ext_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ExtDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for WatchState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                state.selection_changed(id.map(Offer::Wlr));
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id } => {
+                state.primary_selection_changed(id.map(Offer::Wlr));
+            }
+            zwlr_data_control_device_v1::Event::Finished => state.running = false,
+            _ => {}
+        }
+    }
+
+    event_created_child!(WatchState, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtDataControlOfferV1, ()> for WatchState {
+    fn event(
+        _: &mut Self,
+        _: &ExtDataControlOfferV1,
+        _: <ExtDataControlOfferV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for WatchState {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlOfferV1,
+        _: <ZwlrDataControlOfferV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}